@@ -0,0 +1,336 @@
+//! A common interface to a climate device
+//!
+//! `Climate` covers what a bridge to Home Assistant, MQTT or a local display
+//! needs from a unit: what it's doing, and a way to change its settings.
+//! Writing a bridge against the trait lets it be tested against the
+//! emulated `protocol::Device` rather than a real unit. Front ends which
+//! redraw from the whole state at once can work from a `Snapshot`.
+//!
+//! ```
+//! # #[cfg(feature = "device")] {
+//! use mitsu_ac::climate::{Climate, Action};
+//! use mitsu_ac::protocol::Device;
+//! use mitsu_ac::protocol::types::{Mode, Power};
+//!
+//! fn summary<C: Climate>(climate: &C) -> Action {
+//!     climate.action()
+//! }
+//!
+//! let mut device = Device::new();
+//! device.set_power(Power::On).unwrap();
+//! device.set_mode(Mode::Heat).unwrap();
+//! assert_eq!(Action::Idle, summary(&device));
+//!
+//! device.operating = 1;
+//! assert_eq!(Action::Heating, summary(&device));
+//! # }
+//! ```
+
+use core::fmt;
+
+use crate::protocol::{SetRequest, Settings};
+use crate::protocol::types::{Fan, Mode, Power, Rounding, Setpoint, TenthDegreesC};
+
+/// What a unit is actually doing, as opposed to what it's been set to do
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Action {
+    Off,
+    /// On, but with the compressor stopped, e.g. as the setpoint is reached
+    Idle,
+    Heating,
+    Cooling,
+    Drying,
+    Fan,
+}
+
+impl Action {
+    /// Works out what a unit is doing from its `Settings` and the
+    /// `compressor_frequency` and `operating` fields of its last `Status`
+    /// response. The compressor is taken to be running if either is
+    /// non-zero. In auto mode the room is assumed to be heated if it's below
+    /// the setpoint, and cooled otherwise, including when `room` is unknown.
+    ///
+    /// ```
+    /// use mitsu_ac::climate::Action;
+    /// use mitsu_ac::protocol::Settings;
+    /// use mitsu_ac::protocol::types::{Mode, Power};
+    ///
+    /// let settings = Settings { power: Power::On, mode: Mode::Cool, ..Settings::power_on_defaults() };
+    ///
+    /// assert_eq!(Action::Idle, Action::from_status(&settings, None, 0, 0));
+    /// assert_eq!(Action::Cooling, Action::from_status(&settings, None, 38, 1));
+    /// ```
+    pub fn from_status(settings: &Settings, room: Option<TenthDegreesC>, compressor_frequency: u8, operating: u8) -> Self {
+        if settings.power != Power::On {
+            return Action::Off;
+        }
+        let running = compressor_frequency != 0 || operating != 0;
+        match settings.mode {
+            Mode::Fan => Action::Fan,
+            _ if !running => Action::Idle,
+            Mode::Heat => Action::Heating,
+            Mode::Dry => Action::Drying,
+            Mode::Auto if room.is_some_and(|room| room < settings.setpoint.celsius_tenths()) => Action::Heating,
+            _ => Action::Cooling,
+        }
+    }
+}
+
+/// What a unit's controls accept, for rendering them: the modes and fan
+/// speeds it has, and the setpoints allowed in each mode. Units differing
+/// from the default say so through `ProtocolVariant::CLIMATE_TRAITS`.
+///
+/// ```
+/// use mitsu_ac::climate::ClimateTraits;
+/// use mitsu_ac::protocol::types::{Mode, TenthDegreesC};
+///
+/// let traits = ClimateTraits::DEFAULT;
+/// assert_eq!(TenthDegreesC(5), traits.step);
+/// assert_eq!((TenthDegreesC(160), TenthDegreesC(310)), traits.range(Mode::Cool));
+/// assert!(traits.supports_mode(Mode::Dry));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ClimateTraits {
+    pub modes: &'static [Mode],
+    pub fan_speeds: &'static [Fan],
+    /// The smallest change in setpoint the unit accepts
+    pub step: TenthDegreesC,
+    /// The lowest and highest setpoints in heat mode
+    pub heat_range: (TenthDegreesC, TenthDegreesC),
+    /// The lowest and highest setpoints in every other mode
+    pub range: (TenthDegreesC, TenthDegreesC),
+}
+
+impl ClimateTraits {
+    /// The controls of the units this crate was written against, matching
+    /// `Setpoint::range`
+    pub const DEFAULT: Self = ClimateTraits {
+        modes: &[Mode::Heat, Mode::Dry, Mode::Cool, Mode::Fan, Mode::Auto],
+        fan_speeds: &[Fan::Auto, Fan::Quiet, Fan::F1, Fan::F2, Fan::F3, Fan::F4],
+        step: TenthDegreesC(5),
+        heat_range: (Setpoint::MIN, Setpoint::MAX),
+        range: (Setpoint::MIN_COOLING, Setpoint::MAX),
+    };
+
+    /// The lowest and highest setpoints allowed in `mode`
+    pub fn range(&self, mode: Mode) -> (TenthDegreesC, TenthDegreesC) {
+        match mode {
+            Mode::Heat => self.heat_range,
+            _ => self.range,
+        }
+    }
+
+    pub fn supports_mode(&self, mode: Mode) -> bool {
+        self.modes.contains(&mode)
+    }
+
+    pub fn supports_fan(&self, fan: Fan) -> bool {
+        self.fan_speeds.contains(&fan)
+    }
+}
+
+impl Default for ClimateTraits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The unit temperatures are shown in. Units always report in degrees C.
+///
+/// ```
+/// use mitsu_ac::climate::TemperatureUnit;
+/// use mitsu_ac::protocol::types::TenthDegreesC;
+///
+/// let unit = TemperatureUnit::Fahrenheit;
+/// assert_eq!(707, unit.tenths(TenthDegreesC(215)));
+/// assert_eq!("70.7°F", format!("{}", unit.display(TenthDegreesC(215))));
+/// assert_eq!("-0.5°C", format!("{}", TemperatureUnit::Celsius.display(TenthDegreesC(-5))));
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// `temperature` in tenths of a degree of this unit, rounded to the
+    /// nearest tenth
+    pub fn tenths(&self, temperature: TenthDegreesC) -> i32 {
+        let tenths = i32::from(temperature.0);
+        match self {
+            TemperatureUnit::Celsius => tenths,
+            TemperatureUnit::Fahrenheit => {
+                let half = if tenths < 0 { -5 } else { 5 };
+                (tenths * 18 + half) / 10 + 320
+            },
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// `temperature` formatted in this unit to one decimal place, with the
+    /// unit's symbol
+    pub fn display(&self, temperature: TenthDegreesC) -> DisplayTemperature {
+        DisplayTemperature { temperature, unit: *self }
+    }
+}
+
+/// A temperature formatted in a `TemperatureUnit`, from
+/// `TemperatureUnit::display`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DisplayTemperature {
+    temperature: TenthDegreesC,
+    unit: TemperatureUnit,
+}
+
+impl fmt::Display for DisplayTemperature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tenths = self.unit.tenths(self.temperature);
+        let sign = if tenths < 0 { "-" } else { "" };
+        let tenths = tenths.abs();
+        write!(f, "{}{}.{}{}", sign, tenths / 10, tenths % 10, self.unit.symbol())
+    }
+}
+
+/// Everything `Climate` reports about a unit at one moment, for a front end
+/// such as a touchscreen thermostat to bind its widgets to. Taking one each
+/// frame and comparing it with the last shows when to redraw; commands go
+/// back through `Climate`, whose methods return their result directly.
+///
+/// ```
+/// # #[cfg(feature = "device")] {
+/// use mitsu_ac::climate::{Climate, Snapshot, TemperatureUnit};
+/// use mitsu_ac::protocol::Device;
+/// use mitsu_ac::protocol::types::Power;
+///
+/// let mut device = Device::new();
+/// let shown = device.snapshot();
+///
+/// device.set_power(Power::On).unwrap();
+/// assert_ne!(shown, device.snapshot());
+/// assert_eq!(Some(Power::On), device.snapshot().power);
+///
+/// let shown = device.snapshot().with_unit(TemperatureUnit::Fahrenheit);
+/// assert_eq!("71.6°F", shown.display_current_temperature().unwrap().to_string());
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Snapshot {
+    pub current_temperature: Option<TenthDegreesC>,
+    pub target_temperature: Option<Setpoint>,
+    pub power: Option<Power>,
+    pub mode: Option<Mode>,
+    pub fan: Option<Fan>,
+    pub action: Action,
+    /// The unit the front end shows temperatures in, `Celsius` unless set
+    /// with `with_unit`
+    pub unit: TemperatureUnit,
+}
+
+impl Snapshot {
+    pub fn of<C: Climate + ?Sized>(climate: &C) -> Self {
+        Snapshot {
+            current_temperature: climate.current_temperature(),
+            target_temperature: climate.target_temperature(),
+            power: climate.power(),
+            mode: climate.mode(),
+            fan: climate.fan(),
+            action: climate.action(),
+            unit: TemperatureUnit::default(),
+        }
+    }
+
+    pub fn with_unit(self, unit: TemperatureUnit) -> Self {
+        Snapshot { unit, ..self }
+    }
+
+    pub fn display_current_temperature(&self) -> Option<DisplayTemperature> {
+        self.current_temperature.map(|t| self.unit.display(t))
+    }
+
+    pub fn display_target_temperature(&self) -> Option<DisplayTemperature> {
+        self.target_temperature.map(|t| self.unit.display(t.celsius_tenths()))
+    }
+}
+
+/// A climate device which can be monitored and controlled. Values are `None`
+/// until known, e.g. before the unit has first been polled.
+pub trait Climate {
+    type Error;
+
+    fn current_temperature(&self) -> Option<TenthDegreesC>;
+
+    fn target_temperature(&self) -> Option<Setpoint>;
+
+    fn power(&self) -> Option<Power>;
+
+    fn mode(&self) -> Option<Mode>;
+
+    fn fan(&self) -> Option<Fan>;
+
+    fn action(&self) -> Action;
+
+    /// What the unit's controls accept
+    fn traits(&self) -> ClimateTraits {
+        ClimateTraits::DEFAULT
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::of(self)
+    }
+
+    /// Applies every setting present in `request`
+    fn apply(&mut self, request: &SetRequest) -> Result<(), Self::Error>;
+
+    fn set_power(&mut self, power: Power) -> Result<(), Self::Error> {
+        self.apply(&SetRequest { power: Some(power), ..SetRequest::default() })
+    }
+
+    fn set_mode(&mut self, mode: Mode) -> Result<(), Self::Error> {
+        self.apply(&SetRequest::set_mode(mode))
+    }
+
+    fn set_target_temperature(&mut self, setpoint: Setpoint) -> Result<(), Self::Error> {
+        self.apply(&SetRequest::set_temperature(setpoint))
+    }
+
+    /// Moves the setpoint by `delta` from the one last known, stopping at the
+    /// ends of the range `traits` allows in the current mode, and returns the
+    /// setpoint set. The result is rounded to a half degree, away from the
+    /// current setpoint. Turning a rotary encoder can call this once per detent
+    /// without tracking the absolute setpoint itself.
+    ///
+    /// Nothing is sent, and `None` is returned, before the setpoint is known.
+    /// When the mode isn't known either, the narrower range outside of heat
+    /// mode is used.
+    fn adjust_setpoint_by(&mut self, delta: TenthDegreesC) -> Result<Option<Setpoint>, Self::Error> {
+        let current = match self.target_temperature() {
+            Some(setpoint) => setpoint.celsius_tenths(),
+            None => return Ok(None),
+        };
+        let traits = self.traits();
+        let (min, max) = self.mode().map_or(traits.range, |mode| traits.range(mode));
+        // Rounding towards the change, so that a step smaller than the unit's
+        // still moves the setpoint
+        let rounding = match delta.0 {
+            d if d > 0 => Rounding::Ceil,
+            d if d < 0 => Rounding::Floor,
+            _ => Rounding::Nearest,
+        };
+        let setpoint = Setpoint(TenthDegreesC(current.0.saturating_add(delta.0)).clamp(min, max)).rounded(rounding);
+        if setpoint.celsius_tenths() != current {
+            self.set_target_temperature(setpoint)?;
+        }
+        Ok(Some(setpoint))
+    }
+
+    fn set_fan(&mut self, fan: Fan) -> Result<(), Self::Error> {
+        self.apply(&SetRequest { fan: Some(fan), ..SetRequest::default() })
+    }
+}
@@ -0,0 +1,248 @@
+//! Conformance checks against a unit
+//!
+//! Runs through connecting, requesting every `InfoType`, and changing a
+//! setting and reading it back, over anything which can be read from and
+//! written to: a serial port opened at the settings in `link::CN105`, or the
+//! in-crate `Emulator`. The `Report` records how the unit behaved at each
+//! step, which is the first thing to check when trying a new model.
+//!
+//! The port should time out reads, returning `ErrorKind::TimedOut` or
+//! `WouldBlock`, or end of file, when the unit sends nothing, as serial port
+//! crates can be configured to.
+//!
+//! ```
+//! # #[cfg(feature = "device")] {
+//! use mitsu_ac::conformance::{run, Emulator, Outcome};
+//!
+//! let report = run(&mut Emulator::new());
+//! assert_eq!(Outcome::Pass, report.connect);
+//! assert_eq!(Outcome::Pass, report.set_verify);
+//!
+//! let mut out = String::new();
+//! report.write_to(&mut out).unwrap();
+//! # }
+//! ```
+
+use core::fmt;
+use std::io::{self, Read, Write};
+use std::vec::Vec;
+
+use crate::protocol::{
+    encode_to_vec, ConnectRequest, DecodingError, Frame, FrameData, FrameReader, GetInfoRequest,
+    GetInfoResponse, InfoType, SetRequest,
+};
+use crate::protocol::types::Fan;
+
+/// The most bytes read while waiting for one response, so that a line full
+/// of noise can't hold up the run forever
+const MAX_READ: usize = 256;
+
+/// How a unit behaved at one step
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Pass,
+    /// Nothing was received before the read timed out
+    NoResponse,
+    /// A response was received, but not the one expected
+    Unexpected(FrameData),
+    /// The bytes received couldn't be decoded
+    Decoding(DecodingError),
+    /// The setting read back wasn't the one written
+    Mismatch,
+    /// The step wasn't run, as an earlier step it depends on failed
+    Skipped,
+    Io(io::ErrorKind),
+}
+
+/// The outcome of each step of a run
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    pub connect: Outcome,
+    pub info: Vec<(InfoType, Outcome)>,
+    /// Changing the fan speed and reading it back, then changing it back
+    pub set_verify: Outcome,
+}
+
+impl Report {
+    /// Whether every step passed. Some units don't answer every `InfoType`,
+    /// so this is stricter than needed to be usable.
+    pub fn passed(&self) -> bool {
+        self.connect == Outcome::Pass
+            && self.set_verify == Outcome::Pass
+            && self.info.iter().all(|(_, outcome)| *outcome == Outcome::Pass)
+    }
+
+    /// Writes one line per step:
+    ///
+    /// ```text
+    /// connect: Pass
+    /// info Timers: NoResponse
+    /// ```
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        writeln!(w, "connect: {:?}", self.connect)?;
+        for (info_type, outcome) in &self.info {
+            writeln!(w, "info {:?}: {:?}", info_type, outcome)?;
+        }
+        writeln!(w, "set/verify: {:?}", self.set_verify)
+    }
+}
+
+/// Runs every step against the unit on `port`
+pub fn run<P: Read + Write>(port: &mut P) -> Report {
+    let mut reader = FrameReader::new();
+    let mut send = |request: FrameData| exchange(port, &mut reader, request);
+
+    let connect = match send(FrameData::ConnectRequest(ConnectRequest)) {
+        Ok(FrameData::ConnectResponse(_)) => Outcome::Pass,
+        other => failure(other),
+    };
+    if connect != Outcome::Pass {
+        let info = InfoType::ALL.iter().map(|&info_type| (info_type, Outcome::Skipped)).collect();
+        return Report { connect, info, set_verify: Outcome::Skipped };
+    }
+
+    let mut settings = None;
+    let info = InfoType::ALL.iter().map(|&info_type| {
+        let outcome = match send(FrameData::GetInfoRequest(GetInfoRequest::new(info_type))) {
+            Ok(FrameData::GetInfoResponse(response)) => {
+                if let GetInfoResponse::Settings(s) = response {
+                    settings = Some(s);
+                }
+                Outcome::Pass
+            },
+            other => failure(other),
+        };
+        (info_type, outcome)
+    }).collect();
+
+    let set_verify = match settings {
+        Some(settings) => {
+            let changed = if settings.fan == Fan::F2 { Fan::F3 } else { Fan::F2 };
+            let outcome = set_and_verify(&mut send, changed);
+            let _ = set_and_verify(&mut send, settings.fan);
+            outcome
+        },
+        None => Outcome::Skipped,
+    };
+
+    Report { connect, info, set_verify }
+}
+
+fn set_and_verify<F>(send: &mut F, fan: Fan) -> Outcome
+    where F: FnMut(FrameData) -> Result<FrameData, Outcome>
+{
+    match send(FrameData::SetRequest(SetRequest { fan: Some(fan), ..SetRequest::default() })) {
+        Ok(FrameData::SetResponse(_)) => {},
+        other => return failure(other),
+    }
+    match send(FrameData::GetInfoRequest(GetInfoRequest::SETTINGS)) {
+        Ok(FrameData::GetInfoResponse(GetInfoResponse::Settings(settings))) if settings.fan == fan => Outcome::Pass,
+        Ok(FrameData::GetInfoResponse(GetInfoResponse::Settings(_))) => Outcome::Mismatch,
+        other => failure(other),
+    }
+}
+
+fn failure(result: Result<FrameData, Outcome>) -> Outcome {
+    match result {
+        Ok(data) => Outcome::Unexpected(data),
+        Err(outcome) => outcome,
+    }
+}
+
+/// Sends `request` and waits for the next frame received
+fn exchange<P: Read + Write>(port: &mut P, reader: &mut FrameReader, request: FrameData) -> Result<FrameData, Outcome> {
+    let bytes = encode_to_vec(&Frame::from(request)).map_err(|_| Outcome::Io(io::ErrorKind::InvalidInput))?;
+    port.write_all(&bytes).and_then(|_| port.flush()).map_err(|e| Outcome::Io(e.kind()))?;
+
+    reader.clear();
+    let mut buf = [0u8; 32];
+    let mut total = 0;
+    while total < MAX_READ {
+        let len = match port.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => break,
+            Err(e) => return Err(Outcome::Io(e.kind())),
+        };
+        total += len;
+        if let Some(result) = reader.read(&mut buf[..len].iter().copied()) {
+            return result.map_err(Outcome::Decoding);
+        }
+    }
+    Err(Outcome::NoResponse)
+}
+
+/// A `protocol::Device` behind a `Read + Write` port, to run against in
+/// place of a real unit. Each frame written is handled straight away, and
+/// its response is what's read next. Reads return end of file when there's
+/// nothing to read.
+#[cfg(feature = "device")]
+#[derive(Clone, Debug, Default)]
+pub struct Emulator {
+    pub device: crate::protocol::Device,
+    reader: FrameReader,
+    tx: std::collections::VecDeque<u8>,
+}
+
+#[cfg(feature = "device")]
+impl Emulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "device")]
+impl Write for Emulator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for frame in self.reader.frames(buf.iter().copied()).flatten() {
+            if let Some(response) = self.device.handle(&frame) {
+                let bytes = encode_to_vec(&Frame::from(response))
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+                self.tx.extend(bytes);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "device")]
+impl Read for Emulator {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.tx.len());
+        for (out, byte) in buf.iter_mut().zip(self.tx.drain(..len)) {
+            *out = byte;
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(all(test, feature = "device"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emulator_test() {
+        let mut emulator = Emulator::new();
+        let report = run(&mut emulator);
+
+        assert!(!report.passed());
+        assert!(report.info.contains(&(InfoType::Settings, Outcome::Pass)));
+        assert!(report.info.contains(&(InfoType::Timers, Outcome::NoResponse)));
+        assert_eq!(Outcome::Pass, report.set_verify);
+        assert_eq!(Fan::Auto, emulator.device.settings.fan);
+    }
+
+    #[test]
+    fn silent_port_test() {
+        let mut port = io::Cursor::new(Vec::new());
+        let report = run(&mut port);
+
+        assert_eq!(Outcome::NoResponse, report.connect);
+        assert_eq!(Outcome::Skipped, report.set_verify);
+    }
+}
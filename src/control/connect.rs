@@ -0,0 +1,92 @@
+use crate::protocol::{ConnectRequest, FrameData};
+
+/// Sequences the connect handshake ahead of the first request, and again
+/// after the link drops, so an application can send requests without
+/// tracking whether the unit has been connected to.
+///
+/// ```
+/// use mitsu_ac::control::AutoConnect;
+/// use mitsu_ac::protocol::{ConnectRequest, ConnectResponse, FrameData, GetInfoRequest, InfoType};
+///
+/// let mut connect = AutoConnect::new();
+/// let request = FrameData::GetInfoRequest(GetInfoRequest::new(InfoType::Settings));
+///
+/// // Connect first, holding the request back
+/// assert_eq!(Some(FrameData::ConnectRequest(ConnectRequest)), connect.send(request));
+/// assert_eq!(Some(request), connect.receive(&FrameData::ConnectResponse(ConnectResponse::new(0))));
+///
+/// // Then send requests straight away
+/// assert_eq!(Some(request), connect.send(request));
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct AutoConnect {
+    connected: bool,
+    held: Option<FrameData>,
+}
+
+impl AutoConnect {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the frame to send for `request`: the request itself once
+    /// connected, or else a `ConnectRequest`, with `request` held until the
+    /// unit responds. While a `ConnectRequest` is outstanding, nothing is
+    /// returned and `request` replaces the one held.
+    pub fn send(&mut self, request: FrameData) -> Option<FrameData> {
+        if self.connected {
+            return Some(request);
+        }
+        let connecting = self.held.is_some();
+        self.held = Some(request);
+        if connecting { None } else { Some(FrameData::ConnectRequest(ConnectRequest)) }
+    }
+
+    /// Handles a frame received from the unit, returning the held request
+    /// once it has responded to the `ConnectRequest`
+    pub fn receive(&mut self, frame: &FrameData) -> Option<FrameData> {
+        match frame {
+            FrameData::ConnectResponse(_) => {
+                self.connected = true;
+                self.held.take()
+            },
+            _ => None,
+        }
+    }
+
+    /// Marks the link as dropped, e.g. after repeated timeouts or when the
+    /// unit is power cycled, so the next request connects again first. Any
+    /// request held is dropped.
+    pub fn disconnected(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ConnectResponse, SetRequest, SetResponse};
+    use crate::protocol::types::Power;
+
+    #[test]
+    fn reconnect_test() {
+        let mut connect = AutoConnect::new();
+        let on = FrameData::SetRequest(SetRequest { power: Some(Power::On), ..SetRequest::default() });
+        let off = FrameData::SetRequest(SetRequest::power_off());
+
+        assert_eq!(Some(FrameData::ConnectRequest(ConnectRequest)), connect.send(on));
+        assert_eq!(None, connect.send(off));
+        assert_eq!(None, connect.receive(&FrameData::SetResponse(SetResponse)));
+        assert!(!connect.is_connected());
+        assert_eq!(Some(off), connect.receive(&FrameData::ConnectResponse(ConnectResponse::new(0))));
+        assert_eq!(None, connect.receive(&FrameData::ConnectResponse(ConnectResponse::new(0))));
+
+        connect.disconnected();
+        assert!(!connect.is_connected());
+        assert_eq!(Some(FrameData::ConnectRequest(ConnectRequest)), connect.send(on));
+    }
+}
@@ -0,0 +1,86 @@
+/// Holds back rapidly changing values, such as a setpoint being adjusted
+/// with an encoder, until they've stopped changing for `window` ticks. Only
+/// the last value is sent, which saves bus traffic and a beep from the unit
+/// for every step.
+///
+/// ```
+/// use mitsu_ac::control::Debounce;
+/// use mitsu_ac::protocol::types::{Setpoint, TenthDegreesC};
+///
+/// let mut debounce = Debounce::new(5);
+///
+/// for (tick, tenths) in [(0, 210), (2, 215), (4, 220)].iter() {
+///     debounce.change(Setpoint::new(TenthDegreesC(*tenths)).unwrap(), *tick);
+/// }
+///
+/// assert_eq!(None, debounce.poll(8));
+/// assert_eq!(Some(Setpoint::new(TenthDegreesC(220)).unwrap()), debounce.poll(9));
+/// assert_eq!(None, debounce.poll(10));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Debounce<T> {
+    window: u32,
+    pending: Option<(T, u32)>,
+}
+
+impl<T: Copy> Debounce<T> {
+    pub fn new(window: u32) -> Self {
+        Self { window, pending: None }
+    }
+
+    /// Records a new value at tick `now`, restarting the window
+    pub fn change(&mut self, value: T, now: u32) {
+        self.pending = Some((value, now));
+    }
+
+    /// Returns the latest value once `window` ticks have passed since it was
+    /// changed, and then forgets it
+    pub fn poll(&mut self, now: u32) -> Option<T> {
+        match self.pending {
+            Some((value, changed)) if now.wrapping_sub(changed) >= self.window => {
+                self.pending = None;
+                Some(value)
+            },
+            _ => None,
+        }
+    }
+
+    /// The tick at which `poll` will next return a value, if one is pending
+    pub fn deadline(&self) -> Option<u32> {
+        self.pending.map(|(_, changed)| changed.wrapping_add(self.window))
+    }
+
+    /// The value waiting to be sent, if any
+    pub fn pending(&self) -> Option<T> {
+        self.pending.map(|(value, _)| value)
+    }
+
+    /// Drops the pending value without sending it
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_wraparound_test() {
+        let mut debounce = Debounce::new(10);
+        debounce.change(1u8, u32::MAX - 2);
+        assert_eq!(Some(7), debounce.deadline());
+        assert_eq!(None, debounce.poll(6));
+        assert_eq!(Some(1), debounce.poll(7));
+        assert_eq!(None, debounce.deadline());
+    }
+
+    #[test]
+    fn cancel_test() {
+        let mut debounce = Debounce::new(0);
+        debounce.change(1u8, 0);
+        assert_eq!(Some(1), debounce.pending());
+        debounce.cancel();
+        assert_eq!(None, debounce.poll(100));
+    }
+}
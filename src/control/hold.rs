@@ -0,0 +1,110 @@
+use crate::protocol::SetRequest;
+use crate::protocol::types::Setpoint;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+struct ActiveHold {
+    previous: Setpoint,
+    started: u32,
+    duration: u32,
+}
+
+/// A temporary setpoint override which reverts to the previous setpoint
+/// after a set number of ticks
+///
+/// ```
+/// use mitsu_ac::control::Hold;
+/// use mitsu_ac::protocol::types::{Setpoint, TenthDegreesC};
+///
+/// let current = Setpoint::new(TenthDegreesC(200)).unwrap();
+/// let boost = Setpoint::new(TenthDegreesC(240)).unwrap();
+///
+/// let mut hold = Hold::new();
+/// let request = hold.start(current, boost, 0, 3600);
+/// assert_eq!(Some(boost), request.temp);
+///
+/// assert_eq!(None, hold.poll(3599));
+/// assert_eq!(Some(current), hold.poll(3600).unwrap().temp);
+/// assert!(!hold.is_active());
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Hold {
+    active: Option<ActiveHold>,
+}
+
+impl Hold {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts holding `setpoint` for `duration` ticks from `now`, returning
+    /// the request which applies it. `current` is restored when the hold
+    /// ends. Starting a new hold while one is active extends it, and still
+    /// restores the setpoint from before the first.
+    pub fn start(&mut self, current: Setpoint, setpoint: Setpoint, now: u32, duration: u32) -> SetRequest {
+        let previous = self.active.map_or(current, |hold| hold.previous);
+        self.active = Some(ActiveHold { previous, started: now, duration });
+        SetRequest::set_temperature(setpoint)
+    }
+
+    /// Returns the request restoring the previous setpoint once the hold has
+    /// expired
+    pub fn poll(&mut self, now: u32) -> Option<SetRequest> {
+        match self.active {
+            Some(hold) if now.wrapping_sub(hold.started) >= hold.duration => self.cancel(),
+            _ => None,
+        }
+    }
+
+    /// Ends the hold early, returning the request restoring the previous
+    /// setpoint
+    pub fn cancel(&mut self) -> Option<SetRequest> {
+        self.active.take().map(|hold| SetRequest::set_temperature(hold.previous))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// The setpoint which will be restored, if a hold is active
+    pub fn previous(&self) -> Option<Setpoint> {
+        self.active.map(|hold| hold.previous)
+    }
+
+    /// The tick at which the hold expires, if one is active
+    pub fn deadline(&self) -> Option<u32> {
+        self.active.map(|hold| hold.started.wrapping_add(hold.duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::TenthDegreesC;
+
+    fn setpoint(tenths: i16) -> Setpoint {
+        Setpoint::new(TenthDegreesC(tenths)).unwrap()
+    }
+
+    #[test]
+    fn extend_keeps_original_test() {
+        let mut hold = Hold::new();
+        hold.start(setpoint(200), setpoint(230), 0, 10);
+        hold.start(setpoint(230), setpoint(250), 5, 10);
+
+        assert_eq!(Some(setpoint(200)), hold.previous());
+        assert_eq!(Some(15), hold.deadline());
+        assert_eq!(None, hold.poll(14));
+        assert_eq!(Some(setpoint(200)), hold.poll(15).and_then(|r| r.temp));
+    }
+
+    #[test]
+    fn cancel_test() {
+        let mut hold = Hold::new();
+        assert_eq!(None, hold.cancel());
+
+        hold.start(setpoint(200), setpoint(180), u32::MAX, 10);
+        assert_eq!(Some(9), hold.deadline());
+        assert_eq!(Some(setpoint(200)), hold.cancel().and_then(|r| r.temp));
+        assert_eq!(None, hold.poll(9));
+    }
+}
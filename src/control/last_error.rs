@@ -0,0 +1,119 @@
+use crate::protocol::{DataType, DecodingError};
+
+/// A failure talking to the unit
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LinkError {
+    /// A frame from the unit couldn't be decoded
+    Decoding(DecodingError),
+    /// The unit never responded
+    Timeout,
+}
+
+impl From<DecodingError> for LinkError {
+    fn from(error: DecodingError) -> Self {
+        LinkError::Decoding(error)
+    }
+}
+
+/// An error kept by `LastError`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ErrorRecord {
+    pub error: LinkError,
+    /// The type of request which failed, if known
+    pub request: Option<DataType>,
+    /// The tick the error happened at
+    pub tick: u32,
+}
+
+impl ErrorRecord {
+    /// The number of ticks since the error happened
+    pub fn age(&self, now: u32) -> u32 {
+        now.wrapping_sub(self.tick)
+    }
+}
+
+/// Keeps the most recent error talking to the unit, so a UI can show
+/// something like "last communication error: checksum failure 3 min ago"
+/// long after the link has recovered.
+///
+/// ```
+/// use mitsu_ac::control::{LastError, LinkError};
+/// use mitsu_ac::protocol::{DataType, Frame};
+///
+/// let mut errors = LastError::new();
+///
+/// let result = Frame::parse(&[0xfc, 0x62, 0x01, 0x30, 0x01, 0x00, 0x00]);
+/// errors.record_parse(&result, Some(DataType::GetInfoRequest), 1000);
+///
+/// let last = errors.last_error().unwrap();
+/// assert!(matches!(last.error, LinkError::Decoding(_)));
+/// assert_eq!(Some(DataType::GetInfoRequest), last.request);
+/// assert_eq!(180, last.age(1180));
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct LastError {
+    last: Option<ErrorRecord>,
+}
+
+impl LastError {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an error at tick `now`, replacing the one kept before.
+    /// `request` is the type of request which failed, if there was one.
+    pub fn record(&mut self, error: impl Into<LinkError>, request: Option<DataType>, now: u32) {
+        self.last = Some(ErrorRecord { error: error.into(), request, tick: now });
+    }
+
+    /// Records the error from parsing a frame, if there was one. Incomplete
+    /// input isn't an error, as parsing will be retried once more bytes
+    /// arrive.
+    pub fn record_parse<T>(&mut self, result: &Result<T, DecodingError>, request: Option<DataType>, now: u32) {
+        match result {
+            Ok(_) | Err(DecodingError::Incomplete { .. }) => {},
+            Err(error) => self.record(*error, request, now),
+        }
+    }
+
+    pub fn record_timeout(&mut self, request: Option<DataType>, now: u32) {
+        self.record(LinkError::Timeout, request, now);
+    }
+
+    /// The most recent error, if there has been one since the last `clear`
+    pub fn last_error(&self) -> Option<&ErrorRecord> {
+        self.last.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Frame;
+
+    #[test]
+    fn latest_error_kept_test() {
+        let mut errors = LastError::new();
+        assert_eq!(None, errors.last_error());
+
+        errors.record_parse(&Frame::parse(&[0xfc, 0x7a]), None, 10);
+        errors.record_parse(&Frame::parse(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]), None, 20);
+        assert_eq!(None, errors.last_error());
+
+        errors.record_parse(&Frame::parse(&[0x00]), None, 30);
+        errors.record_timeout(Some(DataType::SetRequest), u32::MAX);
+        assert_eq!(Some(&ErrorRecord {
+            error: LinkError::Timeout,
+            request: Some(DataType::SetRequest),
+            tick: u32::MAX,
+        }), errors.last_error());
+        assert_eq!(5, errors.last_error().unwrap().age(4));
+
+        errors.clear();
+        assert_eq!(None, errors.last_error());
+    }
+}
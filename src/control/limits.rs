@@ -0,0 +1,111 @@
+use crate::protocol::SetRequest;
+use crate::protocol::types::{Mode, Power, Setpoint, TenthDegreesC};
+
+/// Hard limits applied to every outgoing `SetRequest`, whatever asked for
+/// it, e.g. for frost protection in an unattended building
+///
+/// ```
+/// use mitsu_ac::control::Limits;
+/// use mitsu_ac::protocol::SetRequest;
+/// use mitsu_ac::protocol::types::{Power, Setpoint, TenthDegreesC};
+///
+/// let limits = Limits {
+///     min_setpoint: TenthDegreesC(160),
+///     frost_protection: Some(TenthDegreesC(50)),
+///     ..Limits::default()
+/// };
+///
+/// let request = SetRequest {
+///     power: Some(Power::Off),
+///     temp: Setpoint::new(TenthDegreesC(100)).ok(),
+///     ..SetRequest::default()
+/// };
+///
+/// let limited = limits.enforce(&request, Some(TenthDegreesC(40)));
+/// assert_eq!(None, limited.power);
+/// assert_eq!(TenthDegreesC(160), limited.temp.unwrap().celsius_tenths());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Limits {
+    /// The lowest setpoint which may be sent
+    pub min_setpoint: TenthDegreesC,
+    /// The highest setpoint which may be sent
+    pub max_setpoint: TenthDegreesC,
+    /// The room temperature below which the unit may not be turned off or
+    /// switched out of heating
+    pub frost_protection: Option<TenthDegreesC>,
+}
+
+impl Default for Limits {
+    /// No limits beyond those of `Setpoint`
+    fn default() -> Self {
+        Self { min_setpoint: Setpoint::MIN, max_setpoint: Setpoint::MAX, frost_protection: None }
+    }
+}
+
+impl Limits {
+    /// Returns `request` with its setpoint clamped to the limits, themselves
+    /// taken to be within `Setpoint::MIN..=Setpoint::MAX`, and, when
+    /// the last known `room` temperature is below `frost_protection`,
+    /// without any change which would stop the unit heating
+    pub fn enforce(&self, request: &SetRequest, room: Option<TenthDegreesC>) -> SetRequest {
+        let mut request = *request;
+
+        let min = self.min_setpoint.clamp(Setpoint::MIN, Setpoint::MAX);
+        let max = self.max_setpoint.clamp(min, Setpoint::MAX);
+        request.temp = request.temp.map(|temp| Setpoint(temp.celsius_tenths().clamp(min, max)));
+
+        if self.is_freezing(room) {
+            if request.power == Some(Power::Off) {
+                request.power = None;
+            }
+            if request.mode.is_some_and(|mode| mode != Mode::Heat) {
+                request.mode = None;
+            }
+        }
+
+        request
+    }
+
+    fn is_freezing(&self, room: Option<TenthDegreesC>) -> bool {
+        match (self.frost_protection, room) {
+            (Some(threshold), Some(room)) => room < threshold,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frost_protection_test() {
+        let limits = Limits { frost_protection: Some(TenthDegreesC(50)), ..Limits::default() };
+        let request = SetRequest { power: Some(Power::Off), mode: Some(Mode::Cool), ..SetRequest::default() };
+
+        assert_eq!(request, limits.enforce(&request, Some(TenthDegreesC(50))));
+        assert_eq!(request, limits.enforce(&request, None));
+        assert_eq!(SetRequest::default(), limits.enforce(&request, Some(TenthDegreesC(49))));
+
+        let heat = SetRequest { mode: Some(Mode::Heat), ..SetRequest::default() };
+        assert_eq!(heat, limits.enforce(&heat, Some(TenthDegreesC(0))));
+    }
+
+    #[test]
+    fn max_setpoint_test() {
+        let limits = Limits { max_setpoint: TenthDegreesC(250), ..Limits::default() };
+        let request = SetRequest { temp: Setpoint::new(TenthDegreesC(300)).ok(), ..SetRequest::default() };
+        assert_eq!(Setpoint::new(TenthDegreesC(250)).ok(), limits.enforce(&request, None).temp);
+    }
+
+    #[test]
+    fn limits_outside_setpoint_range_test() {
+        let limits = Limits { min_setpoint: TenthDegreesC(0), max_setpoint: TenthDegreesC(400), ..Limits::default() };
+        let request = SetRequest { temp: Setpoint::new(TenthDegreesC(300)).ok(), ..SetRequest::default() };
+        assert_eq!(request, limits.enforce(&request, None));
+
+        let limits = Limits { min_setpoint: TenthDegreesC(350), max_setpoint: TenthDegreesC(400), ..Limits::default() };
+        assert_eq!(Some(Setpoint(Setpoint::MAX)), limits.enforce(&request, None).temp);
+    }
+}
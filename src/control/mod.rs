@@ -0,0 +1,35 @@
+//! Client-side helpers for controlling a unit
+//!
+//! These sit between an application and the protocol types: they decide what
+//! to send and keep track of what has been sent, but do no I/O themselves.
+//! Times are plain tick counts in whatever unit the caller uses, as in
+//! `trace`.
+//!
+//! None of them block or keep global state, so each can live in an RTIC
+//! resource and be called from whichever task holds it. Those which act at
+//! a later tick have a `deadline`, and `next_deadline` picks the earliest
+//! for programming a timer.
+
+mod pending;
+mod debounce;
+mod hold;
+mod thermostat;
+mod setback;
+mod limits;
+mod presets;
+mod last_error;
+mod connect;
+mod shutdown;
+mod schedule;
+
+pub use pending::PendingChanges;
+pub use debounce::Debounce;
+pub use hold::Hold;
+pub use thermostat::{Thermostat, ThermostatConfig};
+pub use setback::Setback;
+pub use limits::Limits;
+pub use presets::{Preset, Presets};
+pub use last_error::{LastError, LinkError, ErrorRecord};
+pub use connect::AutoConnect;
+pub use shutdown::{Shutdown, ShutdownResult};
+pub use schedule::next_deadline;
@@ -0,0 +1,130 @@
+use crate::protocol::{SetRequest, Settings};
+use crate::protocol::types::Rounding;
+
+/// Tracks settings changed locally but not yet confirmed by the unit, so a
+/// UI can show them as pending. Record each `SetRequest` as it's sent, and
+/// each `Settings` response as it's received; a change is confirmed once the
+/// unit reports the value it was changed to.
+///
+/// ```
+/// use mitsu_ac::control::PendingChanges;
+/// use mitsu_ac::protocol::{SetRequest, Settings};
+/// use mitsu_ac::protocol::types::{Power, Fan};
+///
+/// let mut pending = PendingChanges::new();
+/// pending.record(&SetRequest { power: Some(Power::On), fan: Some(Fan::F2), ..SetRequest::default() });
+///
+/// let mut settings = Settings::power_on_defaults();
+/// settings.power = Power::On;
+/// pending.confirm(&settings);
+///
+/// // The fan change is still in flight
+/// assert_eq!(None, pending.pending_changes().power);
+/// assert_eq!(Some(Fan::F2), pending.pending_changes().fan);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PendingChanges {
+    pending: SetRequest,
+}
+
+impl PendingChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a request sent to the unit. Its fields replace any pending
+    /// changes to the same settings. A setpoint is kept rounded to the half
+    /// degree it's sent as, which is what the unit reports back.
+    pub fn record(&mut self, request: &SetRequest) {
+        let pending = &mut self.pending;
+        pending.power = request.power.or(pending.power);
+        pending.mode = request.mode.or(pending.mode);
+        pending.temp = request.temp.map(|temp| temp.rounded(Rounding::Nearest)).or(pending.temp);
+        pending.fan = request.fan.or(pending.fan);
+        pending.vane = request.vane.or(pending.vane);
+        pending.widevane = request.widevane.or(pending.widevane);
+    }
+
+    /// Clears the pending changes which `settings` shows have been applied
+    pub fn confirm(&mut self, settings: &Settings) {
+        let pending = &mut self.pending;
+        confirm(&mut pending.power, settings.power);
+        confirm(&mut pending.mode, settings.mode);
+        confirm(&mut pending.temp, settings.setpoint);
+        confirm(&mut pending.fan, settings.fan);
+        confirm(&mut pending.vane, settings.vane);
+        confirm(&mut pending.widevane, settings.widevane);
+    }
+
+    /// The changes not yet confirmed, as a request which would apply them.
+    /// Fields which aren't pending are `None`.
+    pub fn pending_changes(&self) -> &SetRequest {
+        &self.pending
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending == SetRequest::default()
+    }
+
+    /// Forgets every pending change, e.g. after giving up on a request
+    pub fn clear(&mut self) {
+        self.pending = SetRequest::default();
+    }
+}
+
+fn confirm<T: PartialEq>(pending: &mut Option<T>, reported: T) {
+    if pending.as_ref() == Some(&reported) {
+        *pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::{Mode, Setpoint, TenthDegreesC};
+
+    #[test]
+    fn later_requests_replace_test() {
+        let mut pending = PendingChanges::new();
+        assert!(pending.is_empty());
+
+        pending.record(&SetRequest { mode: Some(Mode::Heat), ..SetRequest::default() });
+        pending.record(&SetRequest { mode: Some(Mode::Cool), ..SetRequest::default() });
+
+        let mut settings = Settings::power_on_defaults();
+        settings.mode = Mode::Heat;
+        pending.confirm(&settings);
+        assert_eq!(Some(Mode::Cool), pending.pending_changes().mode);
+
+        settings.mode = Mode::Cool;
+        pending.confirm(&settings);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn setpoint_test() {
+        let mut pending = PendingChanges::new();
+        let setpoint = Setpoint::new(TenthDegreesC(235)).unwrap();
+        pending.record(&SetRequest { temp: Some(setpoint), ..SetRequest::default() });
+
+        let mut settings = Settings::power_on_defaults();
+        settings.setpoint = setpoint;
+        pending.confirm(&settings);
+        assert!(pending.is_empty());
+
+        pending.record(&SetRequest { temp: Some(setpoint), ..SetRequest::default() });
+        pending.clear();
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn off_grid_setpoint_test() {
+        let mut pending = PendingChanges::new();
+        pending.record(&SetRequest { temp: Some(Setpoint::new(TenthDegreesC(213)).unwrap()), ..SetRequest::default() });
+
+        let mut settings = Settings::power_on_defaults();
+        settings.setpoint = Setpoint::new(TenthDegreesC(215)).unwrap();
+        pending.confirm(&settings);
+        assert!(pending.is_empty());
+    }
+}
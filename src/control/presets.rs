@@ -0,0 +1,158 @@
+use crate::protocol::{SetRequest, Settings};
+use crate::protocol::types::{Rounding, Setpoint};
+
+/// A named set of settings, such as "eco" or "boost". Settings left `None`
+/// are left as they are when the preset is applied.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Preset<'a> {
+    pub name: &'a str,
+    pub settings: SetRequest,
+}
+
+/// Applies presets and tracks which one is active. A preset is applied with
+/// a single `SetRequest` holding only the settings it changes, so the unit
+/// takes them all at once. Once the unit has reported the preset's settings,
+/// the preset stays active until a `Settings` response shows one of them
+/// changed, e.g. from the remote.
+///
+/// ```
+/// use mitsu_ac::control::{Preset, Presets};
+/// use mitsu_ac::protocol::{SetRequest, Settings};
+/// use mitsu_ac::protocol::types::{Fan, Setpoint, TenthDegreesC};
+///
+/// let eco = SetRequest {
+///     temp: Setpoint::new(TenthDegreesC(180)).ok(),
+///     fan: Some(Fan::Quiet),
+///     ..SetRequest::default()
+/// };
+/// let presets = [Preset { name: "eco", settings: eco }];
+/// let mut presets = Presets::new(&presets);
+///
+/// let mut settings = Settings::power_on_defaults();
+/// let request = presets.apply("eco", &settings).unwrap();
+/// assert_eq!(None, request.power);
+/// assert_eq!(Some(Fan::Quiet), request.fan);
+///
+/// settings.setpoint = request.temp.unwrap();
+/// settings.fan = Fan::Quiet;
+/// presets.observe(&settings);
+/// assert_eq!(Some("eco"), presets.active().map(|preset| preset.name));
+///
+/// settings.fan = Fan::F4;
+/// presets.observe(&settings);
+/// assert_eq!(None, presets.active());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Presets<'a> {
+    presets: &'a [Preset<'a>],
+    active: Option<Active>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+struct Active {
+    index: usize,
+    /// Whether the unit has reported the preset's settings since it was
+    /// applied; until then, it may just not have caught up
+    confirmed: bool,
+}
+
+impl<'a> Presets<'a> {
+    pub fn new(presets: &'a [Preset<'a>]) -> Self {
+        Self { presets, active: None }
+    }
+
+    pub fn presets(&self) -> &'a [Preset<'a>] {
+        self.presets
+    }
+
+    /// Makes the preset called `name` active, returning the request to send
+    /// to apply it to a unit with `current` settings. `None` if there's no
+    /// such preset.
+    pub fn apply(&mut self, name: &str, current: &Settings) -> Option<SetRequest> {
+        let index = self.presets.iter().position(|preset| preset.name == name)?;
+        self.active = Some(Active { index, confirmed: false });
+        Some(changes(&self.presets[index].settings, current))
+    }
+
+    /// Records the settings reported by the unit, deactivating the active
+    /// preset if they've been changed from it
+    pub fn observe(&mut self, settings: &Settings) {
+        if let Some(active) = &mut self.active {
+            let matches = changes(&self.presets[active.index].settings, settings) == SetRequest::default();
+            if matches {
+                active.confirmed = true;
+            } else if active.confirmed {
+                self.active = None;
+            }
+        }
+    }
+
+    /// The preset last applied, unless the settings have since been changed
+    pub fn active(&self) -> Option<&'a Preset<'a>> {
+        self.active.map(|active| &self.presets[active.index])
+    }
+
+    /// Deactivates the active preset, e.g. when changing a setting locally
+    pub fn clear(&mut self) {
+        self.active = None;
+    }
+}
+
+/// The fields of `request` which differ from `current`. Setpoints are
+/// compared rounded to the half degree they're sent as.
+fn changes(request: &SetRequest, current: &Settings) -> SetRequest {
+    let rounded = |temp: Setpoint| temp.rounded(Rounding::Nearest);
+    SetRequest {
+        power: request.power.filter(|&power| power != current.power),
+        mode: request.mode.filter(|&mode| mode != current.mode),
+        temp: request.temp.filter(|&temp| rounded(temp) != rounded(current.setpoint)),
+        fan: request.fan.filter(|&fan| fan != current.fan),
+        vane: request.vane.filter(|&vane| vane != current.vane),
+        widevane: request.widevane.filter(|&widevane| widevane != current.widevane),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::{Mode, Power, TenthDegreesC};
+
+    #[test]
+    fn unconfirmed_preset_stays_active_test() {
+        let presets = [
+            Preset { name: "away", settings: SetRequest::power_off() },
+            Preset { name: "boost", settings: SetRequest { power: Some(Power::On), mode: Some(Mode::Heat), ..SetRequest::default() } },
+        ];
+        let mut presets = Presets::new(&presets);
+        let mut settings = Settings::power_on_defaults();
+
+        assert_eq!(None, presets.apply("sleep", &settings));
+        assert_eq!(Some(SetRequest::default()), presets.apply("away", &settings));
+
+        let request = presets.apply("boost", &settings).unwrap();
+        assert_eq!((Some(Power::On), Some(Mode::Heat)), (request.power, request.mode));
+        presets.observe(&settings);
+        assert_eq!(Some("boost"), presets.active().map(|preset| preset.name));
+
+        settings.power = Power::On;
+        settings.mode = Mode::Heat;
+        presets.observe(&settings);
+        settings.mode = Mode::Cool;
+        presets.observe(&settings);
+        assert_eq!(None, presets.active());
+    }
+
+    #[test]
+    fn off_grid_setpoint_test() {
+        let setpoint = Setpoint::new(TenthDegreesC(213)).unwrap();
+        let presets = [Preset { name: "comfort", settings: SetRequest::set_temperature(setpoint) }];
+        let mut presets = Presets::new(&presets);
+        let mut settings = Settings::power_on_defaults();
+
+        assert!(presets.apply("comfort", &settings).unwrap().temp.is_some());
+        settings.setpoint = Setpoint::new(TenthDegreesC(215)).unwrap();
+        presets.observe(&settings);
+        assert_eq!(Some(SetRequest::default()), presets.apply("comfort", &settings));
+        assert_eq!(Some("comfort"), presets.active().map(|preset| preset.name));
+    }
+}
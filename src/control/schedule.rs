@@ -0,0 +1,65 @@
+/// The first of `deadlines` to come due after `now`, for programming a
+/// one-shot timer, such as an RTIC monotonic, to wake for exactly the next
+/// action instead of polling every loop. Ticks wrap, so deadlines are
+/// ordered by how far after `now` they fall; one up to half the tick range
+/// before `now` has already passed, and comes first.
+///
+/// ```
+/// use mitsu_ac::control::{next_deadline, Debounce, Hold};
+/// use mitsu_ac::protocol::types::{Setpoint, TenthDegreesC};
+///
+/// let setpoint = Setpoint::new(TenthDegreesC(210)).unwrap();
+///
+/// let mut debounce = Debounce::new(50);
+/// debounce.change(setpoint, 100);
+/// let mut hold = Hold::new();
+/// hold.start(setpoint, setpoint, 0, 3600);
+///
+/// assert_eq!(Some(150), next_deadline(120, [debounce.deadline(), hold.deadline()]));
+/// ```
+pub fn next_deadline<I>(now: u32, deadlines: I) -> Option<u32>
+    where I: IntoIterator<Item = Option<u32>>
+{
+    deadlines.into_iter().flatten().min_by_key(|&deadline| {
+        let until = deadline.wrapping_sub(now);
+        if until > u32::MAX / 2 { 0 } else { until }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraparound_test() {
+        assert_eq!(None, next_deadline(0, [None, None]));
+        assert_eq!(Some(u32::MAX), next_deadline(u32::MAX - 10, [Some(5), None, Some(u32::MAX)]));
+        assert_eq!(Some(5), next_deadline(u32::MAX - 10, [Some(5), Some(20)]));
+
+        // Already passed
+        assert_eq!(Some(u32::MAX), next_deadline(3, [Some(5), Some(u32::MAX)]));
+    }
+
+    #[test]
+    fn settled_thermostat_test() {
+        use crate::control::{Debounce, Thermostat, ThermostatConfig};
+        use crate::protocol::types::{Mode, TenthDegreesC};
+
+        let mut thermostat = Thermostat::new(ThermostatConfig {
+            mode: Mode::Heat,
+            target: TenthDegreesC(200),
+            hysteresis: 5,
+            nudge: 20,
+            min_on: 300,
+            min_off: 300,
+        });
+        thermostat.update(TenthDegreesC(190), 0);
+        let mut debounce = Debounce::new(50);
+        debounce.change(1u8, 500);
+
+        assert_eq!(Some(300), next_deadline(100, [thermostat.deadline(100), debounce.deadline()]));
+        // Once the thermostat's minimum on time has passed, it no longer
+        // holds the timer in the past
+        assert_eq!(Some(550), next_deadline(400, [thermostat.deadline(400), debounce.deadline()]));
+    }
+}
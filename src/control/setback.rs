@@ -0,0 +1,110 @@
+use crate::protocol::{SetRequest, Settings};
+use crate::protocol::types::{Mode, Setpoint, TenthDegreesC};
+
+/// Shifts the setpoint to save energy while a space is unoccupied, and puts
+/// it back when someone returns. Heating setpoints are lowered and cooling
+/// or drying ones raised by `amount`; in other modes the setpoint is left
+/// alone.
+///
+/// ```
+/// use mitsu_ac::control::Setback;
+/// use mitsu_ac::protocol::Settings;
+/// use mitsu_ac::protocol::types::{Mode, Setpoint, TenthDegreesC};
+///
+/// let mut settings = Settings::power_on_defaults();
+/// settings.mode = Mode::Heat;
+/// settings.setpoint = Setpoint::new(TenthDegreesC(210)).unwrap();
+///
+/// let mut setback = Setback::new(40);
+/// let request = setback.set_occupied(false, &settings).unwrap();
+/// assert_eq!(TenthDegreesC(170), request.temp.unwrap().celsius_tenths());
+///
+/// settings.setpoint = request.temp.unwrap();
+/// let request = setback.set_occupied(true, &settings).unwrap();
+/// assert_eq!(TenthDegreesC(210), request.temp.unwrap().celsius_tenths());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Setback {
+    amount: i16,
+    saved: Option<Setpoint>,
+}
+
+impl Setback {
+    /// A setback of `amount` tenths of a degree
+    pub fn new(amount: i16) -> Self {
+        Self { amount, saved: None }
+    }
+
+    /// Handles a change in occupancy, given the unit's current settings,
+    /// returning the request to send, if any. Repeated signals are ignored.
+    pub fn set_occupied(&mut self, occupied: bool, settings: &Settings) -> Option<SetRequest> {
+        let temp = if occupied {
+            self.saved.take()
+        } else if self.saved.is_none() {
+            let temp = self.shifted(settings.setpoint, settings.mode)?;
+            self.saved = Some(settings.setpoint);
+            Some(temp)
+        } else {
+            None
+        };
+        temp.map(SetRequest::set_temperature)
+    }
+
+    /// Whether the setpoint is currently set back
+    pub fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// The setpoint from before the setback, which will be restored on
+    /// occupancy
+    pub fn saved(&self) -> Option<Setpoint> {
+        self.saved
+    }
+
+    /// Forgets the saved setpoint without restoring it, e.g. when the
+    /// setpoint has been changed by hand during the setback
+    pub fn clear(&mut self) {
+        self.saved = None;
+    }
+
+    fn shifted(&self, setpoint: Setpoint, mode: Mode) -> Option<Setpoint> {
+        let shift = match mode {
+            Mode::Heat => -self.amount,
+            Mode::Cool | Mode::Dry => self.amount,
+            _ => return None,
+        };
+        let (min, max) = Setpoint::range(mode);
+        let temp = setpoint.celsius_tenths().0.saturating_add(shift).clamp(min.0, max.0);
+        Setpoint::for_mode(TenthDegreesC(temp), mode).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: Mode, tenths: i16) -> Settings {
+        let mut settings = Settings::power_on_defaults();
+        settings.mode = mode;
+        settings.setpoint = Setpoint::new(TenthDegreesC(tenths)).unwrap();
+        settings
+    }
+
+    #[test]
+    fn cooling_test() {
+        let mut setback = Setback::new(40);
+        let request = setback.set_occupied(false, &settings(Mode::Cool, 290)).unwrap();
+        assert_eq!(Setpoint::new(Setpoint::MAX).ok(), request.temp);
+        assert_eq!(None, setback.set_occupied(false, &settings(Mode::Cool, 310)));
+        let request = setback.set_occupied(true, &settings(Mode::Cool, 310)).unwrap();
+        assert_eq!(Setpoint::new(TenthDegreesC(290)).ok(), request.temp);
+        assert_eq!(None, setback.set_occupied(true, &settings(Mode::Cool, 290)));
+    }
+
+    #[test]
+    fn other_modes_test() {
+        let mut setback = Setback::new(40);
+        assert_eq!(None, setback.set_occupied(false, &settings(Mode::Fan, 220)));
+        assert!(!setback.is_active());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::protocol::{FrameData, SetRequest, Settings};
+
+use super::PendingChanges;
+
+/// How a `Shutdown` ended
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ShutdownResult {
+    /// The unit's settings as of the shutdown: those it last reported, with
+    /// the flushed changes applied if it acknowledged them
+    pub settings: Settings,
+    /// Whether every pending change reached the unit. `false` if the flush
+    /// timed out.
+    pub flushed: bool,
+}
+
+/// Winds a link down before a controller powers off, without losing a change
+/// the user has just made. Polling should stop once it starts; the changes
+/// still pending are sent one last time, and the shutdown completes when the
+/// unit acknowledges them or `timeout` ticks after they're sent.
+///
+/// ```
+/// use mitsu_ac::control::{PendingChanges, Shutdown};
+/// use mitsu_ac::protocol::{FrameData, SetRequest, SetResponse, Settings};
+/// use mitsu_ac::protocol::types::Power;
+///
+/// let mut pending = PendingChanges::new();
+/// pending.record(&SetRequest { power: Some(Power::On), ..SetRequest::default() });
+///
+/// let mut shutdown = Shutdown::new(Settings::power_on_defaults(), &pending, 100);
+/// assert_eq!(Some(Power::On), shutdown.request().unwrap().power);
+/// shutdown.sent(0);
+/// assert_eq!(None, shutdown.poll(10));
+///
+/// shutdown.receive(&FrameData::SetResponse(SetResponse));
+/// let result = shutdown.poll(20).unwrap();
+/// assert!(result.flushed);
+/// assert_eq!(Power::On, result.settings.power);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Shutdown {
+    settings: Settings,
+    flush: Option<SetRequest>,
+    sent: Option<u32>,
+    timeout: u32,
+    acknowledged: bool,
+}
+
+impl Shutdown {
+    /// Starts shutting down, given the settings the unit last reported and
+    /// the changes it hasn't yet confirmed
+    pub fn new(settings: Settings, pending: &PendingChanges, timeout: u32) -> Self {
+        let flush = if pending.is_empty() { None } else { Some(*pending.pending_changes()) };
+        Self { settings, flush, sent: None, timeout, acknowledged: false }
+    }
+
+    /// The request flushing the pending changes, to send once, followed by a
+    /// call to `sent`. `None` if there's nothing to flush.
+    pub fn request(&self) -> Option<&SetRequest> {
+        self.flush.as_ref()
+    }
+
+    /// Records that `request` was sent at tick `now`, starting the timeout
+    pub fn sent(&mut self, now: u32) {
+        self.sent = Some(now);
+    }
+
+    /// Handles a frame received from the unit, watching for the `SetResponse`
+    /// acknowledging the flush. A `SetResponse` before the flush was sent
+    /// answers some earlier request, so is ignored.
+    pub fn receive(&mut self, frame: &FrameData) {
+        if let (FrameData::SetResponse(_), Some(_)) = (frame, self.sent) {
+            self.acknowledged = true;
+        }
+    }
+
+    /// Returns the outcome once the flush has been acknowledged or timed
+    /// out, or straight away if there was nothing to flush
+    pub fn poll(&self, now: u32) -> Option<ShutdownResult> {
+        match self.flush {
+            None => Some(ShutdownResult { settings: self.settings, flushed: true }),
+            Some(request) if self.acknowledged => {
+                Some(ShutdownResult { settings: applied(self.settings, &request), flushed: true })
+            },
+            Some(_) => match self.sent {
+                Some(sent) if now.wrapping_sub(sent) >= self.timeout => {
+                    Some(ShutdownResult { settings: self.settings, flushed: false })
+                },
+                _ => None,
+            },
+        }
+    }
+
+    /// The tick at which the flush times out, if it has been sent and is
+    /// still waiting on the unit
+    pub fn deadline(&self) -> Option<u32> {
+        match (self.flush, self.sent) {
+            (Some(_), Some(sent)) if !self.acknowledged => Some(sent.wrapping_add(self.timeout)),
+            _ => None,
+        }
+    }
+}
+
+fn applied(mut settings: Settings, request: &SetRequest) -> Settings {
+    if let Some(power) = request.power { settings.power = power; }
+    if let Some(mode) = request.mode { settings.mode = mode; }
+    if let Some(temp) = request.temp { settings.setpoint = temp; }
+    if let Some(fan) = request.fan { settings.fan = fan; }
+    if let Some(vane) = request.vane { settings.vane = vane; }
+    if let Some(widevane) = request.widevane { settings.widevane = widevane; }
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SetResponse;
+    use crate::protocol::types::Mode;
+
+    #[test]
+    fn timeout_test() {
+        let mut pending = PendingChanges::new();
+        pending.record(&SetRequest::set_mode(Mode::Cool));
+
+        let mut shutdown = Shutdown::new(Settings::power_on_defaults(), &pending, 10);
+        assert_eq!(None, shutdown.deadline());
+        assert_eq!(None, shutdown.poll(100));
+        shutdown.sent(u32::MAX);
+        assert_eq!(Some(9), shutdown.deadline());
+        assert_eq!(None, shutdown.poll(8));
+        assert_eq!(
+            Some(ShutdownResult { settings: Settings::power_on_defaults(), flushed: false }),
+            shutdown.poll(9)
+        );
+
+        shutdown.receive(&FrameData::Unknown);
+        assert_eq!(Some(9), shutdown.deadline());
+    }
+
+    #[test]
+    fn nothing_pending_test() {
+        let shutdown = Shutdown::new(Settings::power_on_defaults(), &PendingChanges::new(), 10);
+        assert_eq!(None, shutdown.request());
+        assert_eq!(None, shutdown.deadline());
+        assert_eq!(
+            Some(ShutdownResult { settings: Settings::power_on_defaults(), flushed: true }),
+            shutdown.poll(0)
+        );
+    }
+
+    #[test]
+    fn early_ack_test() {
+        let mut pending = PendingChanges::new();
+        pending.record(&SetRequest::set_mode(Mode::Cool));
+
+        // Acknowledging a request sent before the shutdown started
+        let mut shutdown = Shutdown::new(Settings::power_on_defaults(), &pending, 10);
+        shutdown.receive(&FrameData::SetResponse(SetResponse));
+        shutdown.sent(5);
+        assert_eq!(None, shutdown.poll(6));
+        assert_eq!(Some(15), shutdown.deadline());
+
+        shutdown.receive(&FrameData::SetResponse(SetResponse));
+        let result = shutdown.poll(7).unwrap();
+        assert!(result.flushed);
+        assert_eq!(Mode::Cool, result.settings.mode);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::protocol::SetRequest;
+use crate::protocol::types::{Mode, Power, Setpoint, TenthDegreesC};
+
+/// How a `Thermostat` should run the unit
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ThermostatConfig {
+    /// `Mode::Heat` or `Mode::Cool`; any other mode is treated as cooling
+    pub mode: Mode,
+    /// The room temperature to hold
+    pub target: TenthDegreesC,
+    /// How far the room may drift either side of `target`, in tenths of a
+    /// degree, before the unit is turned on or off
+    pub hysteresis: i16,
+    /// How far past `target` to set the unit's own setpoint while it runs,
+    /// so that its own thermostat doesn't cycle it first
+    pub nudge: i16,
+    /// The fewest ticks to leave the unit on once turned on
+    pub min_on: u32,
+    /// The fewest ticks to leave the unit off once turned off
+    pub min_off: u32,
+}
+
+/// An on/off thermostat for use in place of the wall controller. Room
+/// temperature readings are fed in from any sensor, and the unit is turned
+/// on when the room drifts `hysteresis` past `target` and off again once it
+/// has gone `hysteresis` back the other way.
+///
+/// ```
+/// use mitsu_ac::control::{Thermostat, ThermostatConfig};
+/// use mitsu_ac::protocol::types::{Mode, Power, TenthDegreesC};
+///
+/// let mut thermostat = Thermostat::new(ThermostatConfig {
+///     mode: Mode::Heat,
+///     target: TenthDegreesC(200),
+///     hysteresis: 5,
+///     nudge: 20,
+///     min_on: 300,
+///     min_off: 300,
+/// });
+///
+/// let request = thermostat.update(TenthDegreesC(190), 0).unwrap();
+/// assert_eq!(Some(Power::On), request.power);
+/// assert_eq!(TenthDegreesC(220), request.temp.unwrap().celsius_tenths());
+///
+/// // Warm enough, but it hasn't been on for long enough yet
+/// assert_eq!(None, thermostat.update(TenthDegreesC(210), 100));
+/// assert_eq!(Some(Power::Off), thermostat.update(TenthDegreesC(210), 300).unwrap().power);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Thermostat {
+    config: ThermostatConfig,
+    running: bool,
+    switched: Option<u32>,
+}
+
+impl Thermostat {
+    /// A thermostat which assumes the unit is off
+    pub fn new(config: ThermostatConfig) -> Self {
+        Self { config, running: false, switched: None }
+    }
+
+    pub fn config(&self) -> &ThermostatConfig {
+        &self.config
+    }
+
+    /// Changes the target temperature, taking effect at the next `update`.
+    /// While the unit is running, returns the request moving its nudged
+    /// setpoint to match.
+    pub fn set_target(&mut self, target: TenthDegreesC) -> Option<SetRequest> {
+        self.config.target = target;
+        if self.running {
+            self.setpoint().map(SetRequest::set_temperature)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the unit has been turned on
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// The tick at which the unit may next be switched, if as of `now` it
+    /// was switched recently enough that `min_on` or `min_off` is still
+    /// holding it
+    pub fn deadline(&self, now: u32) -> Option<u32> {
+        let min_ticks = if self.running { self.config.min_on } else { self.config.min_off };
+        self.switched
+            .filter(|&switched| now.wrapping_sub(switched) < min_ticks)
+            .map(|switched| switched.wrapping_add(min_ticks))
+    }
+
+    /// Handles a room temperature reading at tick `now`, returning the
+    /// request to send if the unit should be turned on or off
+    pub fn update(&mut self, room: TenthDegreesC, now: u32) -> Option<SetRequest> {
+        let config = &self.config;
+        let heating = config.mode == Mode::Heat;
+        // Positive when the room needs the unit to run
+        let demand = if heating { config.target.0.saturating_sub(room.0) } else { room.0.saturating_sub(config.target.0) };
+
+        let (wanted, min_ticks) = if self.running {
+            (demand > config.hysteresis.saturating_neg(), config.min_on)
+        } else {
+            (demand >= config.hysteresis, config.min_off)
+        };
+        if wanted == self.running {
+            return None;
+        }
+        if let Some(switched) = self.switched {
+            if now.wrapping_sub(switched) < min_ticks {
+                return None;
+            }
+        }
+
+        self.running = wanted;
+        self.switched = Some(now);
+        Some(if wanted { self.on_request() } else { SetRequest::power_off() })
+    }
+
+    fn mode(&self) -> Mode {
+        if self.config.mode == Mode::Heat { Mode::Heat } else { Mode::Cool }
+    }
+
+    /// The target nudged past in the direction the unit runs, within the
+    /// range its mode allows
+    fn setpoint(&self) -> Option<Setpoint> {
+        let config = &self.config;
+        let mode = self.mode();
+        let nudged = if mode == Mode::Heat {
+            config.target.0.saturating_add(config.nudge)
+        } else {
+            config.target.0.saturating_sub(config.nudge)
+        };
+        let (min, max) = Setpoint::range(mode);
+        Setpoint::for_mode(TenthDegreesC(nudged.clamp(min.0, max.0)), mode).ok()
+    }
+
+    fn on_request(&self) -> SetRequest {
+        SetRequest { power: Some(Power::On), mode: Some(self.mode()), temp: self.setpoint(), ..SetRequest::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cooling() -> Thermostat {
+        Thermostat::new(ThermostatConfig {
+            mode: Mode::Cool,
+            target: TenthDegreesC(240),
+            hysteresis: 10,
+            nudge: 100,
+            min_on: 0,
+            min_off: 60,
+        })
+    }
+
+    #[test]
+    fn cooling_test() {
+        let mut thermostat = cooling();
+        assert_eq!(None, thermostat.update(TenthDegreesC(249), 0));
+
+        let request = thermostat.update(TenthDegreesC(250), 1).unwrap();
+        assert_eq!(Some(Mode::Cool), request.mode);
+        assert_eq!(Setpoint::new(Setpoint::MIN_COOLING).ok(), request.temp);
+
+        assert_eq!(None, thermostat.update(TenthDegreesC(231), 2));
+        assert_eq!(Some(Power::Off), thermostat.update(TenthDegreesC(230), 3).unwrap().power);
+        assert!(!thermostat.is_running());
+    }
+
+    #[test]
+    fn min_off_test() {
+        let mut thermostat = cooling();
+        assert_eq!(None, thermostat.deadline(0));
+        thermostat.update(TenthDegreesC(260), u32::MAX - 10);
+        thermostat.update(TenthDegreesC(220), u32::MAX);
+        assert_eq!(Some(59), thermostat.deadline(58));
+        assert_eq!(None, thermostat.deadline(59));
+
+        assert_eq!(None, thermostat.update(TenthDegreesC(260), 58));
+        assert!(thermostat.update(TenthDegreesC(260), 59).is_some());
+    }
+
+    #[test]
+    fn extreme_values_test() {
+        let mut thermostat = Thermostat::new(ThermostatConfig {
+            mode: Mode::Heat,
+            target: TenthDegreesC(i16::MAX),
+            hysteresis: i16::MIN,
+            nudge: i16::MAX,
+            min_on: 0,
+            min_off: 0,
+        });
+        let request = thermostat.update(TenthDegreesC(i16::MIN), 0).unwrap();
+        assert_eq!(Some(Setpoint::MAX), request.temp.map(|temp| temp.celsius_tenths()));
+
+        thermostat.set_target(TenthDegreesC(i16::MIN));
+        let mut cooling = cooling();
+        cooling.set_target(TenthDegreesC(i16::MIN));
+        cooling.update(TenthDegreesC(i16::MAX), 0);
+    }
+
+    #[test]
+    fn set_target_while_running_test() {
+        let mut thermostat = cooling();
+        assert_eq!(None, thermostat.set_target(TenthDegreesC(250)));
+
+        thermostat.update(TenthDegreesC(270), 0).unwrap();
+        let request = thermostat.set_target(TenthDegreesC(260)).unwrap();
+        assert_eq!(Some(TenthDegreesC(160)), request.temp.map(|temp| temp.celsius_tenths()));
+        assert_eq!(None, request.power);
+    }
+}
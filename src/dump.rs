@@ -0,0 +1,162 @@
+//! Annotated hex dumps
+//!
+//! Renders a raw frame one field per line, with the meaning of each byte as
+//! far as it's known from `layout`, for comparing captures against the
+//! protocol notes.
+//! `diff_raw_settings` compares two `Settings` payloads, to see which bytes
+//! a button on the remote changes.
+//!
+//! ```
+//! use mitsu_ac::dump::dump;
+//!
+//! let mut out = String::new();
+//! dump(&[0xfc, 0x42, 0x01, 0x30, 0x01, 0x06, 0x86], &mut out).unwrap();
+//! assert_eq!(out, "\
+//! 00  fc     start
+//! 01  42     data type: GetInfoRequest
+//! 02  01 30  header
+//! 04  01     data length: 1
+//! 05  06     info type
+//! 06  86     checksum: ok
+//! ");
+//! ```
+
+use core::fmt::{self, Write};
+
+use crate::layout::{Layout, Meaning, Span};
+use crate::protocol::{verify_checksum, DataType, DecodingError, InfoType, HEADER_LEN};
+
+/// Writes `frame` as an annotated hex dump, one field per line, each line
+/// starting with the offset of its first byte in hex. Runs of bytes with no known
+/// meaning are grouped together as "unknown". A frame cut short is dumped as
+/// far as it goes; bytes after the checksum are shown as trailing.
+pub fn dump<W: Write>(frame: &[u8], w: &mut W) -> fmt::Result {
+    let header = &frame[..frame.len().min(HEADER_LEN)];
+    if let Some(&start) = header.first() {
+        line(w, 0, &[start], format_args!("start"))?;
+    }
+    if let Some(&data_type) = header.get(1) {
+        line(w, 1, &[data_type], format_args!("data type: {:?}", DataType::from_byte(data_type)))?;
+    }
+    if header.len() > 2 {
+        line(w, 2, &header[2..header.len().min(4)], format_args!("header"))?;
+    }
+    let data_len = match header.get(4) {
+        Some(&data_len) => data_len as usize,
+        None => return Ok(()),
+    };
+    line(w, 4, &[header[4]], format_args!("data length: {}", data_len))?;
+
+    let data = &frame[HEADER_LEN..frame.len().min(HEADER_LEN + data_len)];
+    let layout = Layout::of(DataType::from_byte(frame[1]), data);
+    let mut offset = 0;
+    while offset < data.len() {
+        let span = layout.and_then(|layout| layout.span_at(offset));
+        let (end, name) = match span {
+            Some(Span { offset: start, len, meaning: Meaning::Known(name) }) => (start + len, *name),
+            Some(Span { offset: start, len, meaning: Meaning::Unknown }) => (start + len, "unknown"),
+            None => (data.len(), "unknown"),
+        };
+        let end = end.min(data.len());
+        line(w, HEADER_LEN + offset, &data[offset..end], format_args!("{}", name))?;
+        offset = end;
+    }
+
+    let end = HEADER_LEN + data_len;
+    if let Some(&received) = frame.get(end) {
+        match verify_checksum(&frame[..=end]) {
+            Err(DecodingError::InvalidChecksum { calculated, .. }) =>
+                line(w, end, &[received], format_args!("checksum: BAD (calculated {:02x})", calculated))?,
+            _ => line(w, end, &[received], format_args!("checksum: ok"))?,
+        }
+    }
+    if frame.len() > end + 1 {
+        line(w, end + 1, &frame[end + 1..], format_args!("trailing"))?;
+    }
+    Ok(())
+}
+
+/// A byte which differs between two payloads, from `diff_raw_settings`.
+/// A byte missing from the shorter payload is `None`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ByteDiff {
+    pub offset: usize,
+    pub a: Option<u8>,
+    pub b: Option<u8>,
+    /// The field the byte belongs to, if known
+    pub field: Option<&'static str>,
+}
+
+/// Compares the data of two `Settings` responses, starting with their info
+/// type byte, yielding each byte which differs
+///
+/// ```
+/// use mitsu_ac::dump::diff_raw_settings;
+///
+/// let before = [0x02, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xac, 0x00, 0x00, 0x00, 0x00];
+/// let mut after = before;
+/// after[6] = 0x03;
+/// after[14] = 0x80;
+///
+/// let mut diff = diff_raw_settings(&before, &after);
+/// assert_eq!(Some("fan"), diff.next().unwrap().field);
+/// let unknown = diff.next().unwrap();
+/// assert_eq!((14, None), (unknown.offset, unknown.field));
+/// assert_eq!(None, diff.next());
+/// ```
+pub fn diff_raw_settings<'a>(a: &'a [u8], b: &'a [u8]) -> impl Iterator<Item = ByteDiff> + 'a {
+    let settings = Layout::find(DataType::GetInfoResponse, Some(InfoType::Settings));
+    (0..a.len().max(b.len()))
+        .map(move |offset| ByteDiff {
+            offset,
+            a: a.get(offset).copied(),
+            b: b.get(offset).copied(),
+            field: match settings.and_then(|layout| layout.span_at(offset)) {
+                Some(Span { meaning: Meaning::Known(name), .. }) => Some(*name),
+                _ => None,
+            },
+        })
+        .filter(|diff| diff.a != diff.b)
+}
+
+/// Writes one line of the dump, padding short fields so that their labels
+/// line up
+fn line<W: Write>(w: &mut W, offset: usize, bytes: &[u8], label: fmt::Arguments<'_>) -> fmt::Result {
+    write!(w, "{:02x} ", offset)?;
+    for b in bytes {
+        write!(w, " {:02x}", b)?;
+    }
+    for _ in bytes.len()..2 {
+        write!(w, "   ")?;
+    }
+    writeln!(w, "  {}", label)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::String;
+
+    #[test]
+    fn dump_test() {
+        let mut out = String::new();
+        dump(&[0xfc, 0x62, 0x01, 0x30, 0x10, 0x06, 0x00, 0x00, 0x2a, 0x01,
+               0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], &mut out).unwrap();
+        assert!(out.contains("\n08  2a     compressor frequency\n09  01     operating\n"));
+        assert!(out.contains("\n0a  00 00 00 00 00 00 00 00 00 00 00  unknown\n"));
+        assert!(out.ends_with("\n15  00     checksum: BAD (calculated 2c)\n16  00     trailing\n"));
+
+        out.clear();
+        dump(&[0xfc, 0x62, 0x01], &mut out).unwrap();
+        assert_eq!(out, "00  fc     start\n01  62     data type: GetInfoResponse\n02  01     header\n");
+    }
+
+    #[test]
+    fn diff_lengths_test() {
+        let mut diff = diff_raw_settings(&[0x02, 0x00, 0x00, 0x01], &[0x02, 0x00, 0x00]);
+        assert_eq!(Some(ByteDiff { offset: 3, a: Some(0x01), b: None, field: Some("power") }), diff.next());
+        assert_eq!(None, diff.next());
+    }
+}
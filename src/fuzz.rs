@@ -0,0 +1,120 @@
+//! Fuzzing entry points
+//!
+//! Each function takes arbitrary bytes and runs them through the decoders,
+//! and in `fuzz_roundtrip` the encoders too, panicking if any of their
+//! guarantees is broken. A cargo-fuzz target only needs to call one:
+//!
+//! ```ignore
+//! #![no_main]
+//! libfuzzer_sys::fuzz_target!(|data: &[u8]| mitsu_ac::fuzz::fuzz_roundtrip(data));
+//! ```
+//!
+//! Keeping the checks here keeps them in step with the crate's API.
+
+use crate::protocol::encoding::SizedEncoding;
+use crate::protocol::{
+    verify_checksum, DataType, Encodable, Frame, FrameData, FrameReader, MAX_FRAME_LEN,
+};
+
+/// Decodes `data` as raw bytes from the serial line, in every way the crate
+/// offers, checking that none of them panics or reads past its input
+pub fn fuzz_parse_frame(data: &[u8]) {
+    if let Ok((rest, frame)) = Frame::parse(data) {
+        assert!(rest.len() < data.len());
+        let _ = FrameData::parse_exact(frame);
+    }
+    let _ = Frame::parse_complete(data);
+    let _ = Frame::parse_until(data);
+    let _ = verify_checksum(data);
+
+    let (consumed, _) = Frame::scan(data);
+    assert!(consumed <= data.len());
+
+    let mut reader = FrameReader::new();
+    for &byte in data {
+        let _ = reader.push(byte);
+        assert!(reader.buffered() <= MAX_FRAME_LEN);
+    }
+
+    if let Ok((rest, _)) = FrameData::parse_untrusted(data) {
+        assert!(rest.len() < data.len());
+    }
+    let _ = FrameData::parse_complete(data);
+}
+
+/// Decodes the data of a frame whose data type is the first byte of `data`
+/// and whose data is the rest, checking that no more data is consumed than
+/// was given
+pub fn fuzz_parse_frame_data(data: &[u8]) {
+    let frame = match frame(data) {
+        Some(frame) => frame,
+        None => return,
+    };
+    if let Ok((rest, _)) = FrameData::parse(frame) {
+        assert!(rest.len() <= frame.data.len());
+    }
+    let _ = FrameData::parse_exact(frame);
+}
+
+/// Decodes a frame as `fuzz_parse_frame_data` does, then encodes it and
+/// decodes it again, checking that the encoded frame is valid, decodes with
+/// nothing left over, and encodes again to the same bytes
+pub fn fuzz_roundtrip(data: &[u8]) {
+    let decoded = match frame(data).map(FrameData::parse) {
+        Some(Ok((_, decoded))) => decoded,
+        _ => return,
+    };
+
+    let mut first = [0u8; MAX_FRAME_LEN];
+    let len = match encode(decoded, &mut first) {
+        Some(len) => len,
+        None => return,
+    };
+    let first = &first[..len];
+
+    let (rest, frame) = Frame::parse(first).expect("encoded frame doesn't parse");
+    assert!(rest.is_empty());
+    let redecoded = FrameData::parse_exact(frame).expect("encoded data doesn't decode exactly");
+
+    let mut second = [0u8; MAX_FRAME_LEN];
+    let len = encode(redecoded, &mut second).expect("decoded data doesn't encode");
+    assert_eq!(first, &second[..len]);
+}
+
+fn frame(data: &[u8]) -> Option<Frame<&[u8]>> {
+    let (&data_type, data) = data.split_first()?;
+    Some(Frame::new(DataType::from_byte(data_type), data))
+}
+
+/// Encodes `data` as a frame, if it can be encoded at all
+fn encode(data: FrameData, into: &mut [u8]) -> Option<usize> {
+    let frame = Frame::from(data);
+    frame.encode(into.get_mut(..frame.length())?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift64;
+
+    #[test]
+    fn pseudo_random_inputs_test() {
+        let mut rng = XorShift64::new(0x2545_f491_4f6c_dd1d);
+        let mut data = [0u8; 24];
+        for i in 0..20_000 {
+            for byte in data.iter_mut() {
+                *byte = rng.next_u64() as u8;
+            }
+            // Start with a known data type and info type often enough to
+            // reach the decoders
+            let types = [0x41, 0x42, 0x5a, 0x61, 0x62, 0x7a];
+            data[0] = types[i % types.len()];
+            data[1] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06][i / types.len() % 6];
+
+            let len = (rng.next_u64() >> 40) as usize % data.len();
+            fuzz_parse_frame(&data[..len]);
+            fuzz_parse_frame_data(&data[..len]);
+            fuzz_roundtrip(&data[..len]);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Home Assistant climate strings
+//!
+//! Converts between this crate's settings and the strings used by Home
+//! Assistant's climate entities, e.g. in the `mode_state_topic` and
+//! `mode_command_topic` of an MQTT climate. Bridges should use these rather
+//! than keeping their own tables, so that they all agree.
+//!
+//! ```
+//! use mitsu_ac::hass;
+//! use mitsu_ac::protocol::SetRequest;
+//! use mitsu_ac::protocol::types::{Mode, Power};
+//!
+//! assert_eq!(Some("heat_cool"), hass::hvac_mode(Power::On, Mode::Auto));
+//! assert_eq!(Some("off"), hass::hvac_mode(Power::Off, Mode::Heat));
+//!
+//! let request = hass::parse_hvac_mode("fan_only").unwrap();
+//! assert_eq!(Some(Power::On), request.power);
+//! assert_eq!(Some(Mode::Fan), request.mode);
+//! ```
+
+use crate::climate::Action;
+use crate::protocol::SetRequest;
+use crate::protocol::types::{Fan, Mode, Power, Vane, WideVane};
+
+/// The HVAC mode shown for a unit. Power is folded in, as Home Assistant has
+/// no separate on/off state: a unit which is off is `"off"` whatever its
+/// mode. `None` for modes Home Assistant has no name for.
+pub fn hvac_mode(power: Power, mode: Mode) -> Option<&'static str> {
+    match (power, mode) {
+        (Power::Off, _) => Some("off"),
+        (Power::On, Mode::Heat) => Some("heat"),
+        (Power::On, Mode::Cool) => Some("cool"),
+        (Power::On, Mode::Dry) => Some("dry"),
+        (Power::On, Mode::Fan) => Some("fan_only"),
+        (Power::On, Mode::Auto) => Some("heat_cool"),
+        _ => None,
+    }
+}
+
+/// The request to send for an HVAC mode command. Anything but `"off"` also
+/// turns the unit on. `"auto"` is accepted as well as `"heat_cool"`, as
+/// both are offered by some front ends.
+pub fn parse_hvac_mode(s: &str) -> Option<SetRequest> {
+    let mode = match s {
+        "off" => return Some(SetRequest::power_off()),
+        "heat" => Mode::Heat,
+        "cool" => Mode::Cool,
+        "dry" => Mode::Dry,
+        "fan_only" => Mode::Fan,
+        "heat_cool" | "auto" => Mode::Auto,
+        _ => return None,
+    };
+    Some(SetRequest { power: Some(Power::On), mode: Some(mode), ..SetRequest::default() })
+}
+
+/// The HVAC action shown for a unit
+pub fn hvac_action(action: Action) -> &'static str {
+    match action {
+        Action::Off => "off",
+        Action::Idle => "idle",
+        Action::Heating => "heating",
+        Action::Cooling => "cooling",
+        Action::Drying => "drying",
+        Action::Fan => "fan",
+    }
+}
+
+/// The fan mode shown for a fan speed. The four numbered speeds become
+/// `"low"`, `"medium"`, `"high"` and `"top"`.
+pub fn fan_mode(fan: Fan) -> Option<&'static str> {
+    match fan {
+        Fan::Auto => Some("auto"),
+        Fan::Quiet => Some("quiet"),
+        Fan::F1 => Some("low"),
+        Fan::F2 => Some("medium"),
+        Fan::F3 => Some("high"),
+        Fan::F4 => Some("top"),
+        Fan::Other(_) => None,
+    }
+}
+
+/// The fan speed for a fan mode command, the reverse of `fan_mode`
+pub fn parse_fan_mode(s: &str) -> Option<Fan> {
+    match s {
+        "auto" => Some(Fan::Auto),
+        "quiet" => Some(Fan::Quiet),
+        "low" => Some(Fan::F1),
+        "medium" => Some(Fan::F2),
+        "high" => Some(Fan::F3),
+        "top" => Some(Fan::F4),
+        _ => None,
+    }
+}
+
+/// The swing mode shown for a unit's vanes: `"vertical"` when the vane
+/// swings, `"horizontal"` when the wide vane does, `"both"` or `"off"`
+pub fn swing_mode(vane: Vane, widevane: WideVane) -> &'static str {
+    match (vane == Vane::Swing, widevane == WideVane::Swing) {
+        (true, true) => "both",
+        (true, false) => "vertical",
+        (false, true) => "horizontal",
+        (false, false) => "off",
+    }
+}
+
+/// The vane positions for a swing mode command, the reverse of
+/// `swing_mode`. A vane which isn't to swing is set to `Vane::Auto` or
+/// `WideVane::Center`, as swing modes don't say where it should point.
+pub fn parse_swing_mode(s: &str) -> Option<(Vane, WideVane)> {
+    match s {
+        "off" => Some((Vane::Auto, WideVane::Center)),
+        "vertical" => Some((Vane::Swing, WideVane::Center)),
+        "horizontal" => Some((Vane::Auto, WideVane::Swing)),
+        "both" => Some((Vane::Swing, WideVane::Swing)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        for &mode in &[Mode::Heat, Mode::Cool, Mode::Dry, Mode::Fan, Mode::Auto] {
+            let request = parse_hvac_mode(hvac_mode(Power::On, mode).unwrap()).unwrap();
+            assert_eq!(Some(mode), request.mode);
+        }
+        assert_eq!(None, hvac_mode(Power::On, Mode::Other(0x09)));
+        assert_eq!(Some(SetRequest::power_off()), parse_hvac_mode("off"));
+
+        for &fan in &[Fan::Auto, Fan::Quiet, Fan::F1, Fan::F2, Fan::F3, Fan::F4] {
+            assert_eq!(Some(fan), parse_fan_mode(fan_mode(fan).unwrap()));
+        }
+        assert_eq!(None, parse_fan_mode("turbo"));
+
+        for &swing in &["off", "vertical", "horizontal", "both"] {
+            let (vane, widevane) = parse_swing_mode(swing).unwrap();
+            assert_eq!(swing, swing_mode(vane, widevane));
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Fault injection
+//!
+//! `FaultInjector` wraps a `Read + Write` port, such as a serial port or the
+//! `conformance::Emulator`, and corrupts the bytes passing through it the way
+//! a noisy line would: dropping, duplicating, bit-flipping or delaying them.
+//! The faults are pseudo-random from a seed, so a failure found in CI can be
+//! reproduced by running again with the same seed.
+//!
+//! ```
+//! use std::io::{Cursor, Read};
+//! use mitsu_ac::inject::{FaultInjector, Faults};
+//!
+//! let faults = Faults { flip: 1000, ..Faults::NONE };
+//! let mut port = FaultInjector::new(Cursor::new(vec![0x00; 8]), faults, 42);
+//!
+//! let mut buf = [0u8; 8];
+//! assert_eq!(8, port.read(&mut buf).unwrap());
+//! assert!(buf.iter().all(|&b| b.count_ones() == 1));
+//! ```
+
+use std::io::{self, Read, Write};
+use std::vec::Vec;
+
+use crate::rng::XorShift64;
+
+/// How often each fault happens, in bytes per thousand. Each byte suffers at
+/// most one fault, checked in the order of the fields.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Faults {
+    /// The byte is lost
+    pub drop: u16,
+    /// The byte arrives twice
+    pub duplicate: u16,
+    /// One bit of the byte is flipped
+    pub flip: u16,
+    /// The byte, and the rest of the read with it, is held back until the
+    /// next read, as if it arrived late. Only applies to bytes read.
+    pub delay: u16,
+}
+
+impl Faults {
+    pub const NONE: Self = Faults { drop: 0, duplicate: 0, flip: 0, delay: 0 };
+}
+
+/// A port which injects `Faults` into the bytes written to and read from
+/// `inner`
+#[derive(Debug)]
+pub struct FaultInjector<P> {
+    inner: P,
+    faults: Faults,
+    rng: XorShift64,
+    delayed: Vec<u8>,
+}
+
+impl<P> FaultInjector<P> {
+    pub fn new(inner: P, faults: Faults, seed: u64) -> Self {
+        Self { inner, faults, rng: XorShift64::new(seed), delayed: Vec::new() }
+    }
+
+    pub fn faults(&self) -> Faults {
+        self.faults
+    }
+
+    /// Changes the faults injected from now on, e.g. to stop injecting them
+    /// and check that a link recovers
+    pub fn set_faults(&mut self, faults: Faults) {
+        self.faults = faults;
+    }
+
+    pub fn get_ref(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// Whether something happening `per_thousand` times in a thousand
+    /// happens this time
+    fn chance(&mut self, per_thousand: u16) -> bool {
+        per_thousand > 0 && self.rng.next_u64() % 1000 < u64::from(per_thousand)
+    }
+
+    /// Passes `bytes` through the faults, returning where in `bytes` a delay
+    /// starts, if one does
+    fn corrupt(&mut self, bytes: &[u8], out: &mut Vec<u8>, delay: bool) -> Option<usize> {
+        for (i, &byte) in bytes.iter().enumerate() {
+            if self.chance(self.faults.drop) {
+                continue;
+            }
+            if self.chance(self.faults.duplicate) {
+                out.extend_from_slice(&[byte, byte]);
+            } else if self.chance(self.faults.flip) {
+                let bit = self.rng.next_u64() % 8;
+                out.push(byte ^ (1 << bit));
+            } else if delay && i > 0 && self.chance(self.faults.delay) {
+                return Some(i);
+            } else {
+                out.push(byte);
+            }
+        }
+        None
+    }
+}
+
+impl<P: Write> Write for FaultInjector<P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut corrupted = Vec::with_capacity(buf.len() * 2);
+        self.corrupt(buf, &mut corrupted, false);
+        self.inner.write_all(&corrupted)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<P: Read> Read for FaultInjector<P> {
+    /// Bytes held back by a delay are returned on their own, before anything
+    /// more is read from `inner`. If every byte read is dropped, `inner` is
+    /// read again, so that only its end of file is returned as one.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut received = core::mem::take(&mut self.delayed);
+        let mut raw = std::vec![0u8; buf.len()];
+        while received.is_empty() {
+            let len = self.inner.read(&mut raw)?;
+            if len == 0 {
+                return Ok(0);
+            }
+            if let Some(start) = self.corrupt(&raw[..len], &mut received, true) {
+                self.delayed = raw[start..len].to_vec();
+                // With every byte before the delay dropped, there's nothing
+                // for the delayed bytes to arrive after, and reading more
+                // would put newer bytes ahead of them
+                if received.is_empty() {
+                    received = core::mem::take(&mut self.delayed);
+                }
+            }
+        }
+
+        // Duplicates can make more than fits; keep the rest for next time
+        let len = received.len().min(buf.len());
+        buf[..len].copy_from_slice(&received[..len]);
+        if len < received.len() {
+            let mut rest = received.split_off(len);
+            rest.append(&mut self.delayed);
+            self.delayed = rest;
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all<P: Read>(port: &mut P) -> Vec<u8> {
+        let mut all = Vec::new();
+        let mut buf = [0u8; 4];
+        loop {
+            match port.read(&mut buf).unwrap() {
+                0 => return all,
+                len => all.extend_from_slice(&buf[..len]),
+            }
+        }
+    }
+
+    #[test]
+    fn no_faults_test() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let mut port = FaultInjector::new(io::Cursor::new(bytes.clone()), Faults::NONE, 1);
+        assert_eq!(bytes, read_all(&mut port));
+
+        let mut port = FaultInjector::new(Vec::new(), Faults::NONE, 1);
+        port.write_all(&bytes).unwrap();
+        assert_eq!(bytes, port.into_inner());
+    }
+
+    #[test]
+    fn seeded_test() {
+        let faults = Faults { drop: 100, duplicate: 100, flip: 100, delay: 100 };
+        let bytes: Vec<u8> = (0..=255).collect();
+        let run = |seed| read_all(&mut FaultInjector::new(io::Cursor::new(bytes.clone()), faults, seed));
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(bytes, run(7));
+    }
+
+    /// Returns at most two bytes per read, as a slow serial port might
+    struct Trickle(io::Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(2);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn drop_and_delay_keep_order_test() {
+        let faults = Faults { drop: 500, delay: 500, ..Faults::NONE };
+        let bytes: Vec<u8> = (0..=255).collect();
+        for seed in 1..50 {
+            let mut port = FaultInjector::new(Trickle(io::Cursor::new(bytes.clone())), faults, seed);
+            let received = read_all(&mut port);
+            assert!(received.windows(2).all(|w| w[0] < w[1]), "seed {}: {:?}", seed, received);
+        }
+    }
+
+    #[test]
+    fn delay_test() {
+        let faults = Faults { delay: 1000, ..Faults::NONE };
+        let mut port = FaultInjector::new(io::Cursor::new(std::vec![1, 2, 3]), faults, 1);
+        let mut buf = [0u8; 4];
+
+        assert_eq!(1, port.read(&mut buf).unwrap());
+        assert_eq!(2, port.read(&mut buf).unwrap());
+        assert_eq!([2, 3], buf[..2]);
+    }
+}
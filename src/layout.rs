@@ -0,0 +1,234 @@
+//! Byte layouts of each packet
+//!
+//! A description of every byte of the data of each kind of frame, and what
+//! it's known to mean, for tooling to query. Bytes whose meaning hasn't been
+//! worked out yet are included as `Meaning::Unknown`, so that they can be
+//! listed and chipped away at.
+//!
+//! ```
+//! use mitsu_ac::layout::{Layout, Meaning};
+//! use mitsu_ac::protocol::{DataType, InfoType};
+//!
+//! let status = Layout::find(DataType::GetInfoResponse, Some(InfoType::Status)).unwrap();
+//! assert_eq!(Meaning::Known("compressor frequency"), status.span_at(3).unwrap().meaning);
+//!
+//! let unknown: usize = status.unknown().map(|span| span.len).sum();
+//! assert_eq!(13, unknown);
+//! ```
+
+use crate::protocol::{DataType, InfoType};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Meaning {
+    Known(&'static str),
+    Unknown,
+}
+
+/// A run of bytes within a frame's data, starting `offset` bytes in
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+    pub meaning: Meaning,
+}
+
+/// The layout of the data of one kind of frame. Its spans are in order and
+/// cover every byte, from the start of the data to `len()`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Layout {
+    pub name: &'static str,
+    pub data_type: DataType,
+    /// For `GetInfoResponse`s, the info type in the first byte of the data
+    pub info_type: Option<InfoType>,
+    pub spans: &'static [Span],
+}
+
+const fn known(offset: usize, len: usize, name: &'static str) -> Span {
+    Span { offset, len, meaning: Meaning::Known(name) }
+}
+
+const fn unknown(offset: usize, len: usize) -> Span {
+    Span { offset, len, meaning: Meaning::Unknown }
+}
+
+/// Every layout known
+pub const LAYOUTS: &[Layout] = &[
+    Layout {
+        name: "SetRequest",
+        data_type: DataType::SetRequest,
+        info_type: None,
+        spans: &[
+            known(0, 1, "packet type"),
+            known(1, 1, "flags"),
+            known(2, 1, "widevane flag"),
+            known(3, 1, "power"),
+            known(4, 1, "mode"),
+            known(5, 1, "setpoint (mapped)"),
+            known(6, 1, "fan"),
+            known(7, 1, "vane"),
+            unknown(8, 5),
+            known(13, 1, "widevane"),
+            known(14, 1, "setpoint (half degrees)"),
+            unknown(15, 1),
+        ],
+    },
+    Layout {
+        name: "GetInfoRequest",
+        data_type: DataType::GetInfoRequest,
+        info_type: None,
+        spans: &[
+            known(0, 1, "info type"),
+            known(1, 15, "padding"),
+        ],
+    },
+    Layout {
+        name: "ConnectRequest",
+        data_type: DataType::ConnectRequest,
+        info_type: None,
+        spans: &[
+            known(0, 2, "connect payload"),
+        ],
+    },
+    Layout {
+        name: "SetResponse",
+        data_type: DataType::SetResponse,
+        info_type: None,
+        spans: &[
+            unknown(0, 16),
+        ],
+    },
+    Layout {
+        name: "Settings",
+        data_type: DataType::GetInfoResponse,
+        info_type: Some(InfoType::Settings),
+        spans: &[
+            known(0, 1, "info type"),
+            unknown(1, 2),
+            known(3, 1, "power"),
+            known(4, 1, "mode and i-see"),
+            known(5, 1, "setpoint (mapped)"),
+            known(6, 1, "fan"),
+            known(7, 1, "vane"),
+            unknown(8, 2),
+            known(10, 1, "widevane"),
+            known(11, 1, "setpoint (half degrees)"),
+            unknown(12, 4),
+        ],
+    },
+    Layout {
+        name: "RoomTemp",
+        data_type: DataType::GetInfoResponse,
+        info_type: Some(InfoType::RoomTemp),
+        spans: &[
+            known(0, 1, "info type"),
+            unknown(1, 2),
+            known(3, 1, "room temperature (mapped)"),
+            unknown(4, 2),
+            known(6, 1, "room temperature (half degrees)"),
+            unknown(7, 9),
+        ],
+    },
+    Layout {
+        name: "Faults",
+        data_type: DataType::GetInfoResponse,
+        info_type: Some(InfoType::Type4),
+        spans: &[
+            known(0, 1, "info type"),
+            unknown(1, 3),
+            known(4, 2, "error code"),
+            known(6, 1, "fault code"),
+            unknown(7, 9),
+        ],
+    },
+    Layout {
+        name: "Timers",
+        data_type: DataType::GetInfoResponse,
+        info_type: Some(InfoType::Timers),
+        spans: &[
+            known(0, 1, "info type"),
+            unknown(1, 15),
+        ],
+    },
+    Layout {
+        name: "Status",
+        data_type: DataType::GetInfoResponse,
+        info_type: Some(InfoType::Status),
+        spans: &[
+            known(0, 1, "info type"),
+            unknown(1, 2),
+            known(3, 1, "compressor frequency"),
+            known(4, 1, "operating"),
+            unknown(5, 11),
+        ],
+    },
+    Layout {
+        name: "ConnectResponse",
+        data_type: DataType::ConnectResponse,
+        info_type: None,
+        spans: &[
+            unknown(0, 1),
+        ],
+    },
+];
+
+impl Layout {
+    /// The layout of frames of `data_type`, and for `GetInfoResponse`s,
+    /// `info_type`
+    pub fn find(data_type: DataType, info_type: Option<InfoType>) -> Option<&'static Layout> {
+        LAYOUTS.iter().find(|layout| {
+            layout.data_type == data_type
+                && (data_type != DataType::GetInfoResponse || layout.info_type == info_type)
+        })
+    }
+
+    /// The layout of a frame of `data_type` with `data`, taking the info type
+    /// of a `GetInfoResponse` from its first byte
+    pub fn of(data_type: DataType, data: &[u8]) -> Option<&'static Layout> {
+        Self::find(data_type, data.first().map(|&b| InfoType::from_byte(b)))
+    }
+
+    /// The length of the data described
+    pub fn len(&self) -> usize {
+        self.spans.last().map_or(0, |span| span.offset + span.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The span holding the byte at `offset`
+    pub fn span_at(&self, offset: usize) -> Option<&'static Span> {
+        self.spans.iter().find(|span| (span.offset..span.offset + span.len).contains(&offset))
+    }
+
+    /// The spans whose meaning isn't known
+    pub fn unknown(&self) -> impl Iterator<Item = &'static Span> {
+        self.spans.iter().filter(|span| span.meaning == Meaning::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_cover_data_test() {
+        for layout in LAYOUTS {
+            let mut next = 0;
+            for span in layout.spans {
+                assert_eq!(next, span.offset, "{}", layout.name);
+                assert!(span.len > 0, "{}", layout.name);
+                next += span.len;
+            }
+        }
+    }
+
+    #[test]
+    fn find_test() {
+        assert_eq!("Settings", Layout::of(DataType::GetInfoResponse, &[0x02]).unwrap().name);
+        assert_eq!("SetRequest", Layout::of(DataType::SetRequest, &[0x01]).unwrap().name);
+        assert_eq!(None, Layout::of(DataType::GetInfoResponse, &[0x09]));
+        assert_eq!(None, Layout::of(DataType::GetInfoResponse, &[]));
+        assert_eq!(16, Layout::find(DataType::SetRequest, None).unwrap().len());
+    }
+}
@@ -13,7 +13,44 @@
 //! There is no code to actually interface with a serial device here. The CN105
 //! serial connection operates at 2400 baud, 8 bits per byte, even parity with 1
 //! stop bit (2400 8E1). You should configure your serial peripheral as such,
-//! and use this library to parse/encode data on that line.
+//! and use this library to parse/encode data on that line. `link::CN105`
+//! describes this configuration, and can check yours against it.
+//!
+//! ## Optional Features
+//!
+//! * `full` (default) - both of the following:
+//!   * `controller` - decoding of responses and encoding of requests, as
+//!     needed to control a unit
+//!   * `device` - decoding of requests, as needed to stand in for a unit
+//!
+//!   At least one of `controller` and `device` is required. Leaving one out
+//!   compiles out its parsers to save flash.
+//! * `nom` (default) - parse using [nom](https://crates.io/crates/nom). With
+//!   it disabled, a small hand-written parser with the same behaviour is used
+//!   instead, which saves a few KB of flash on small targets.
+//! * `modbus` - a Modbus register map on top of the protocol types
+//! * `control` - client-side helpers for deciding what to send to a unit
+//! * `dump` - annotated hex dumps of frames, for reverse engineering
+//! * `trace` - ring buffers recording recent traffic, and checks of its
+//!   timing, for diagnosing problems in the field
+//! * `fuzz` - entry points for fuzzing the decoders and encoders (implies
+//!   `full`)
+//! * `heapless` - `ByteSink` and other impls for
+//!   [heapless](https://crates.io/crates/heapless) collections
+//! * `alloc` - conveniences for targets with a heap, such as `encode_to_vec`,
+//!   `OwnedFrameData` and a growable `RxBuffer`
+//! * `std` - helpers for hosted targets, such as Prometheus metrics rendering
+//!   a conformance suite for checking new models, and fault injection for
+//!   testing links against a noisy line (implies `alloc`)
+//! * `python` - Python bindings for offline analysis (implies `std`)
+//!
+//! ## Stack Usage
+//!
+//! Parsing uses at most `protocol::PARSE_STACK_BOUND` bytes of stack in a
+//! release build, which leaves room on parts with only a couple of KB of RAM.
+//! Debug builds use several times that, so build this crate with
+//! optimisations (e.g. `[profile.dev.package.mitsu-ac] opt-level = "s"`) if
+//! your stack is that small.
 //!
 //! ## General Usage
 //!
@@ -92,10 +129,57 @@
 //! }
 //! ```
 
+#[cfg(not(any(feature = "controller", feature = "device")))]
+compile_error!("at least one of the `controller` and `device` features must be enabled");
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "nom")]
 #[macro_use]
 extern crate nom;
 
 pub mod protocol;
+pub mod stats;
+pub mod link;
+pub mod climate;
+pub mod hass;
+pub mod layout;
+pub mod runtime;
+pub mod zone;
+
+#[cfg(feature = "modbus")]
+pub mod modbus;
+
+#[cfg(feature = "control")]
+pub mod control;
+
+#[cfg(feature = "dump")]
+pub mod dump;
+
+#[cfg(feature = "trace")]
+pub mod trace;
+
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+
+#[cfg(feature = "std")]
+pub mod metrics;
+
+#[cfg(all(feature = "std", feature = "controller"))]
+pub mod conformance;
+
+#[cfg(feature = "std")]
+pub mod inject;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+#[cfg(any(test, feature = "std"))]
+mod rng;
 
 #[doc(inline)]
 pub use protocol::*;
@@ -0,0 +1,148 @@
+//! Serial link configuration
+//!
+//! The CN105 connector runs at 2400 baud, 8 data bits, even parity and one
+//! stop bit. Anything else tends to show up as a stream of checksum failures
+//! or no frames at all, so it's worth checking the serial peripheral's
+//! configuration against `CN105` once at startup.
+//!
+//! ```
+//! use mitsu_ac::link::{self, SerialConfig, Parity, StopBits, ConfigError};
+//!
+//! let uart = SerialConfig { baud_rate: 9600, data_bits: 8, parity: Parity::None, stop_bits: StopBits::One };
+//!
+//! assert_eq!(
+//!     Err(ConfigError::BaudRate { expected: 2400, actual: 9600 }),
+//!     link::CN105.validate(&uart)
+//! );
+//! assert_eq!(Ok(()), link::CN105.validate(&link::CN105.serial));
+//! ```
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// The settings of a serial peripheral
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl SerialConfig {
+    /// The bits sent on the line for each byte, including start, parity and
+    /// stop bits
+    pub fn bits_per_byte(&self) -> u32 {
+        let parity = if self.parity == Parity::None { 0 } else { 1 };
+        let stop = match self.stop_bits { StopBits::One => 1, StopBits::Two => 2 };
+        1 + self.data_bits as u32 + parity + stop
+    }
+
+    /// How long it takes to send `bytes` bytes back-to-back, in milliseconds,
+    /// rounded up
+    pub fn transmit_time_ms(&self, bytes: usize) -> u32 {
+        let bits = bytes as u64 * self.bits_per_byte() as u64;
+        let baud_rate = (self.baud_rate as u64).max(1);
+        (bits * 1000).div_ceil(baud_rate) as u32
+    }
+}
+
+/// A serial configuration which doesn't match the one a link needs
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ConfigError {
+    BaudRate { expected: u32, actual: u32 },
+    DataBits { expected: u8, actual: u8 },
+    Parity { expected: Parity, actual: Parity },
+    StopBits { expected: StopBits, actual: StopBits },
+}
+
+/// Everything needed to drive a link: the serial settings plus recommended
+/// timings. The timings follow
+/// [SwiCago/HeatPump](https://github.com/SwiCago/HeatPump).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct LinkConfig {
+    pub serial: SerialConfig,
+    /// How long to wait for the response to a request before giving up on it
+    pub response_timeout_ms: u32,
+    /// The least time to leave between the end of one frame sent and the
+    /// start of the next
+    pub inter_frame_gap_ms: u32,
+    /// How long to wait after connecting before sending anything else
+    pub connect_delay_ms: u32,
+}
+
+/// The configuration of a CN105 link
+pub const CN105: LinkConfig = LinkConfig {
+    serial: SerialConfig {
+        baud_rate: 2400,
+        data_bits: 8,
+        parity: Parity::Even,
+        stop_bits: StopBits::One,
+    },
+    response_timeout_ms: 1000,
+    inter_frame_gap_ms: 1000,
+    connect_delay_ms: 2000,
+};
+
+impl LinkConfig {
+    /// Checks `serial` against the settings the link needs, returning the
+    /// first mismatch found
+    pub fn validate(&self, serial: &SerialConfig) -> Result<(), ConfigError> {
+        let expected = &self.serial;
+        if serial.baud_rate != expected.baud_rate {
+            return Err(ConfigError::BaudRate { expected: expected.baud_rate, actual: serial.baud_rate });
+        }
+        if serial.data_bits != expected.data_bits {
+            return Err(ConfigError::DataBits { expected: expected.data_bits, actual: serial.data_bits });
+        }
+        if serial.parity != expected.parity {
+            return Err(ConfigError::Parity { expected: expected.parity, actual: serial.parity });
+        }
+        if serial.stop_bits != expected.stop_bits {
+            return Err(ConfigError::StopBits { expected: expected.stop_bits, actual: serial.stop_bits });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_test() {
+        let mut serial = CN105.serial;
+        assert_eq!(Ok(()), CN105.validate(&serial));
+
+        serial.parity = Parity::None;
+        assert_eq!(
+            Err(ConfigError::Parity { expected: Parity::Even, actual: Parity::None }),
+            CN105.validate(&serial)
+        );
+
+        serial.parity = Parity::Even;
+        serial.stop_bits = StopBits::Two;
+        assert_eq!(
+            Err(ConfigError::StopBits { expected: StopBits::One, actual: StopBits::Two }),
+            CN105.validate(&serial)
+        );
+    }
+
+    #[test]
+    fn transmit_time_test() {
+        assert_eq!(11, CN105.serial.bits_per_byte());
+        // A 22 byte GetInfoResponse frame
+        assert_eq!(101, CN105.serial.transmit_time_ms(22));
+        assert_eq!(0, CN105.serial.transmit_time_ms(0));
+    }
+}
@@ -0,0 +1,119 @@
+//! Metrics text exposition
+//!
+//! Renders link counters and the latest readings from the device in the
+//! Prometheus text format, so a gateway daemon can serve them on `/metrics`.
+//!
+//! ```
+//! use mitsu_ac::metrics::Metrics;
+//! use mitsu_ac::protocol::GetInfoResponse;
+//!
+//! let mut metrics = Metrics::default();
+//! metrics.update(&GetInfoResponse::Status { compressor_frequency: 42, operating: 1 });
+//! metrics.stats.record_sent();
+//!
+//! let text = metrics.render();
+//! assert!(text.contains("mitsu_ac_compressor_frequency 42\n"));
+//! assert!(text.contains("mitsu_ac_frames_sent_total 1\n"));
+//! ```
+
+use core::fmt::{self, Write};
+use std::string::String;
+
+use crate::protocol::GetInfoResponse;
+use crate::protocol::types::TenthDegreesC;
+use crate::stats::Stats;
+
+/// Counters and latest readings to expose
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub room_temperature: Option<TenthDegreesC>,
+    pub compressor_frequency: Option<u8>,
+    pub stats: Stats,
+}
+
+impl Metrics {
+    /// Records the readings carried by a response from the device
+    pub fn update(&mut self, response: &GetInfoResponse) {
+        match response {
+            GetInfoResponse::RoomTemperature { temperature } =>
+                self.room_temperature = Some(temperature.celsius_tenths()),
+            GetInfoResponse::Status { compressor_frequency, .. } =>
+                self.compressor_frequency = Some(*compressor_frequency),
+            _ => {},
+        }
+    }
+
+    /// Writes the metrics in Prometheus text format. Readings which haven't
+    /// been received yet are left out.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> fmt::Result {
+        if let Some(TenthDegreesC(tenths)) = self.room_temperature {
+            header(w, "mitsu_ac_room_temperature_celsius", "gauge", "Room temperature reported by the unit")?;
+            let sign = if tenths < 0 { "-" } else { "" };
+            let tenths = tenths.unsigned_abs();
+            writeln!(w, "mitsu_ac_room_temperature_celsius {}{}.{}", sign, tenths / 10, tenths % 10)?;
+        }
+        if let Some(frequency) = self.compressor_frequency {
+            header(w, "mitsu_ac_compressor_frequency", "gauge", "Compressor frequency reported by the unit")?;
+            writeln!(w, "mitsu_ac_compressor_frequency {}", frequency)?;
+        }
+
+        let counters = [
+            ("mitsu_ac_frames_parsed_total", "Frames successfully parsed", self.stats.frames_parsed),
+            ("mitsu_ac_frames_sent_total", "Frames transmitted", self.stats.frames_sent),
+            ("mitsu_ac_checksum_failures_total", "Frames rejected due to an invalid checksum", self.stats.checksum_failures),
+            ("mitsu_ac_resyncs_total", "Times junk was skipped to find the next frame", self.stats.resyncs),
+            ("mitsu_ac_retries_total", "Requests re-sent after a failure or timeout", self.stats.retries),
+            ("mitsu_ac_timeouts_total", "Requests which never received a response", self.stats.timeouts),
+        ];
+        for (name, help, value) in counters.iter() {
+            header(w, name, "counter", help)?;
+            writeln!(w, "{} {}", name, value)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the metrics in Prometheus text format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        // Writing into a String can't fail
+        let _ = self.write_to(&mut text);
+        text
+    }
+}
+
+fn header<W: Write>(w: &mut W, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(w, "# HELP {} {}", name, help)?;
+    writeln!(w, "# TYPE {} {}", name, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::Temperature;
+
+    #[test]
+    fn render_test() {
+        let mut metrics = Metrics::default();
+        metrics.stats.checksum_failures = 3;
+        let text = metrics.render();
+        assert!(text.starts_with(
+            "# HELP mitsu_ac_frames_parsed_total Frames successfully parsed\n\
+             # TYPE mitsu_ac_frames_parsed_total counter\n\
+             mitsu_ac_frames_parsed_total 0\n"
+        ));
+        assert!(text.contains(
+            "# HELP mitsu_ac_checksum_failures_total Frames rejected due to an invalid checksum\n\
+             # TYPE mitsu_ac_checksum_failures_total counter\n\
+             mitsu_ac_checksum_failures_total 3\n"
+        ));
+
+        metrics.update(&GetInfoResponse::RoomTemperature {
+            temperature: Temperature::HalfDegreesCPlusOffset { value: 0xab },
+        });
+        assert!(metrics.render().starts_with(
+            "# HELP mitsu_ac_room_temperature_celsius Room temperature reported by the unit\n\
+             # TYPE mitsu_ac_room_temperature_celsius gauge\n\
+             mitsu_ac_room_temperature_celsius 21.5\n"
+        ));
+    }
+}
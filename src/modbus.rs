@@ -0,0 +1,198 @@
+//! Modbus register map
+//!
+//! Translates between a flat set of Modbus holding registers and this crate's
+//! protocol types, so a Modbus RTU/TCP front-end can sit on top of a CN105
+//! link. There is no Modbus framing here; feed register reads/writes from
+//! whatever Modbus stack you use.
+//!
+//! | Address | Register | Access | Units |
+//! |---------|----------|--------|-------|
+//! | 0 | Power | RW | `0` = off, `1` = on |
+//! | 1 | Mode | RW | raw `Mode` byte |
+//! | 2 | Setpoint | RW | tenths of a degree C |
+//! | 3 | Room temperature | R | tenths of a degree C, signed |
+//! | 4 | Compressor frequency | R | raw byte |
+//! | 5 | Operating | R | raw byte |
+//!
+//! ```
+//! use mitsu_ac::modbus::{self, RegisterMap};
+//! use mitsu_ac::protocol::GetInfoResponse;
+//! use mitsu_ac::protocol::types::Temperature;
+//!
+//! let mut registers = RegisterMap::new();
+//! registers.update(&GetInfoResponse::RoomTemperature {
+//!     temperature: Temperature::HalfDegreesCPlusOffset { value: 0xaa },
+//! });
+//! assert_eq!(Ok(210), registers.read(modbus::ROOM_TEMP));
+//!
+//! // A write to the setpoint register becomes a `SetRequest` for the device
+//! let request = modbus::write_request(modbus::SETPOINT, 215).unwrap();
+//! assert!(request.temp.is_some());
+//! ```
+
+use core::convert::TryFrom;
+
+use crate::protocol::{GetInfoResponse, SetRequest, Settings};
+use crate::protocol::types::{Power, Mode, Setpoint, TenthDegreesC};
+use crate::protocol::encoding::OneByteEncodable;
+
+pub const POWER: u16 = 0;
+pub const MODE: u16 = 1;
+pub const SETPOINT: u16 = 2;
+pub const ROOM_TEMP: u16 = 3;
+pub const COMPRESSOR_FREQUENCY: u16 = 4;
+pub const OPERATING: u16 = 5;
+
+/// Number of registers in the map, starting at address 0
+pub const REGISTER_COUNT: usize = 6;
+
+/// Lowest setpoint accepted by a write to `SETPOINT`, in tenths of a degree C
+pub const SETPOINT_MIN: u16 = Setpoint::MIN.0 as u16;
+/// Highest setpoint accepted by a write to `SETPOINT`, in tenths of a degree C
+pub const SETPOINT_MAX: u16 = Setpoint::MAX.0 as u16;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ModbusError {
+    /// The register doesn't exist, or is read-only and was written to
+    IllegalAddress,
+    /// The value written can't be represented by the device
+    IllegalValue,
+}
+
+impl ModbusError {
+    /// The Modbus exception code to report back to the master
+    pub fn exception_code(&self) -> u8 {
+        match self {
+            ModbusError::IllegalAddress => 0x02,
+            ModbusError::IllegalValue => 0x03,
+        }
+    }
+}
+
+/// The latest known register values, kept up to date from the device's
+/// `GetInfoResponse`s
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct RegisterMap {
+    registers: [u16; REGISTER_COUNT],
+}
+
+impl RegisterMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a single register
+    pub fn read(&self, address: u16) -> Result<u16, ModbusError> {
+        self.registers.get(address as usize).copied().ok_or(ModbusError::IllegalAddress)
+    }
+
+    /// Reads `into.len()` consecutive registers starting at `start`
+    pub fn read_range(&self, start: u16, into: &mut [u16]) -> Result<(), ModbusError> {
+        let start = start as usize;
+        let end = start + into.len();
+        match self.registers.get(start..end) {
+            Some(registers) => {
+                into.copy_from_slice(registers);
+                Ok(())
+            },
+            None => Err(ModbusError::IllegalAddress),
+        }
+    }
+
+    /// Updates the registers from a response received from the device.
+    /// Responses that don't map onto any register are ignored.
+    pub fn update(&mut self, response: &GetInfoResponse) {
+        match response {
+            GetInfoResponse::Settings(Settings { power, mode, setpoint, .. }) => {
+                self.registers[POWER as usize] = power.encoded_as_byte() as u16;
+                self.registers[MODE as usize] = mode.encoded_as_byte() as u16;
+                self.registers[SETPOINT as usize] = tenths(setpoint.celsius_tenths());
+            },
+            GetInfoResponse::RoomTemperature { temperature } => {
+                self.registers[ROOM_TEMP as usize] = tenths(temperature.celsius_tenths());
+            },
+            GetInfoResponse::Status { compressor_frequency, operating } => {
+                self.registers[COMPRESSOR_FREQUENCY as usize] = *compressor_frequency as u16;
+                self.registers[OPERATING as usize] = *operating as u16;
+            },
+            GetInfoResponse::Faults { .. } | GetInfoResponse::Unknown => {},
+        }
+    }
+}
+
+// Negative temperatures are represented as two's complement, as is usual for
+// signed Modbus registers
+fn tenths(temperature: TenthDegreesC) -> u16 {
+    temperature.0 as u16
+}
+
+/// Translates a write to a single register into the `SetRequest` that
+/// applies it on the device
+pub fn write_request(address: u16, value: u16) -> Result<SetRequest, ModbusError> {
+    let mut request = SetRequest::default();
+
+    match address {
+        POWER => request.power = Some(byte(value).and_then(|b| Power::try_from(b).ok()).ok_or(ModbusError::IllegalValue)?),
+        MODE => request.mode = Some(byte(value).and_then(|b| Mode::try_from(b).ok()).ok_or(ModbusError::IllegalValue)?),
+        SETPOINT => {
+            if !(SETPOINT_MIN..=SETPOINT_MAX).contains(&value) {
+                return Err(ModbusError::IllegalValue);
+            }
+            request.temp = Some(Setpoint::new(TenthDegreesC(value as i16)).map_err(|_| ModbusError::IllegalValue)?);
+        },
+        _ => return Err(ModbusError::IllegalAddress),
+    }
+
+    Ok(request)
+}
+
+fn byte(value: u16) -> Option<u8> {
+    if value > 0xff { None } else { Some(value as u8) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::{Fan, Vane, WideVane, ISee};
+
+    #[test]
+    fn update_settings_test() {
+        let mut registers = RegisterMap::new();
+        registers.update(&GetInfoResponse::Settings(Settings {
+            power: Power::On,
+            mode: Mode::Cool,
+            setpoint: Setpoint::new(TenthDegreesC(210)).unwrap(),
+            fan: Fan::Auto,
+            vane: Vane::Auto,
+            widevane: WideVane::Center,
+            isee: ISee::Off,
+        }));
+
+        let mut values = [0u16; 3];
+        assert_eq!(Ok(()), registers.read_range(POWER, &mut values));
+        assert_eq!([1, 0x03, 210], values);
+    }
+
+    #[test]
+    fn read_out_of_range_test() {
+        let registers = RegisterMap::new();
+        let mut values = [0u16; 2];
+        assert_eq!(Err(ModbusError::IllegalAddress), registers.read(REGISTER_COUNT as u16));
+        assert_eq!(Err(ModbusError::IllegalAddress), registers.read_range(OPERATING, &mut values));
+    }
+
+    #[test]
+    fn write_request_test() {
+        assert_eq!(Some(Power::Off), write_request(POWER, 0).unwrap().power);
+        assert_eq!(Some(Mode::Heat), write_request(MODE, 0x01).unwrap().mode);
+        assert_eq!(
+            Setpoint::new(TenthDegreesC(215)).ok(),
+            write_request(SETPOINT, 215).unwrap().temp
+        );
+
+        assert_eq!(Err(ModbusError::IllegalValue), write_request(POWER, 2));
+        assert_eq!(Err(ModbusError::IllegalValue), write_request(MODE, 0x0100));
+        assert_eq!(Err(ModbusError::IllegalValue), write_request(SETPOINT, 90));
+        assert_eq!(Err(ModbusError::IllegalAddress), write_request(ROOM_TEMP, 200));
+    }
+}
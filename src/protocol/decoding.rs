@@ -0,0 +1,123 @@
+#[cfg(feature = "nom")]
+use nom::Needed;
+
+use super::frame::DataType;
+use super::frame_data::InfoType;
+use super::types::ImpossibleTemperature;
+
+/// Errors encountered while decoding a `Frame` or its `FrameData`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DecodingError {
+    /// The input ended before a whole frame could be read. `needed` is the
+    /// number of extra bytes required, if known; reading more from the serial
+    /// line and trying again may succeed.
+    Incomplete { needed: Option<usize> },
+    /// The input ended before a whole frame could be read, when it was
+    /// parsed as complete with one of the `parse_complete` functions.
+    /// `needed` is as for `Incomplete`, and is `None` when no frame was
+    /// started at all.
+    Truncated { needed: Option<usize> },
+    /// The input doesn't start with a frame header
+    BadHeader,
+    /// The checksum byte received doesn't match the one calculated from the
+    /// frame's contents. The frame's header fields are included to help tell
+    /// line noise apart from a misunderstood frame.
+    InvalidChecksum {
+        data_type: DataType,
+        data_len: usize,
+        calculated: u8,
+        received: u8,
+    },
+    /// The frame's data is shorter than its data type requires
+    ShortData { needed: Option<usize> },
+    /// The frame's data is longer than its data type accounts for; `len` is
+    /// the number of bytes left over. Only returned by
+    /// `FrameData::parse_exact`.
+    TrailingData { data_type: DataType, len: usize },
+    /// The frame's data couldn't be decoded as its data type
+    InvalidData(DataType),
+    /// The frame's data contradicts itself, such as a `SetRequest` flagging a
+    /// setting without including a value for it
+    InconsistentData(DataType),
+    /// The data of a `GetInfoResponse` with a supported `InfoType` couldn't be
+    /// decoded, usually because it was truncated. Responses with unsupported
+    /// `InfoType`s aren't an error, and decode to `GetInfoResponse::Unknown`.
+    MalformedInfo(InfoType),
+    /// A temperature byte in the frame's data is one no unit would send, such
+    /// as a setpoint below 0°C
+    ImpossibleTemperature(ImpossibleTemperature),
+}
+
+/// A byte which doesn't correspond to any value of the named type, returned
+/// by the strict `TryFrom<u8>` conversions
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnknownValue {
+    pub type_name: &'static str,
+    pub byte: u8,
+}
+
+/// The result of decoding: the remaining input and the decoded value
+pub type DecodingResult<'a, T> = Result<(&'a [u8], T), DecodingError>;
+
+/// The result of one of the internal parsers: the remaining input and the
+/// parsed value. Shared by the nom and hand-written parsers, so that either
+/// can be compiled in.
+pub(crate) type ParseResult<'a, T> = Result<(&'a [u8], T), ParseError>;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ParseError {
+    /// The input ended early; the number of bytes needed, if known
+    Incomplete(Option<usize>),
+    /// The input can't be parsed
+    Invalid,
+    /// The input parsed, but contradicts itself
+    #[cfg_attr(not(feature = "device"), allow(dead_code))]
+    Inconsistent,
+    /// The input parsed, but holds a temperature no unit would send
+    ImpossibleTemperature(ImpossibleTemperature),
+}
+
+impl From<ImpossibleTemperature> for ParseError {
+    fn from(e: ImpossibleTemperature) -> Self {
+        ParseError::ImpossibleTemperature(e)
+    }
+}
+
+/// Converts the result of a nom parser
+#[cfg(feature = "nom")]
+pub(crate) fn from_nom<T>(result: nom::IResult<&[u8], T>) -> ParseResult<'_, T> {
+    result.map_err(|e| match e {
+        nom::Err::Incomplete(Needed::Size(n)) => ParseError::Incomplete(Some(n)),
+        nom::Err::Incomplete(Needed::Unknown) => ParseError::Incomplete(None),
+        nom::Err::Error(_) | nom::Err::Failure(_) => ParseError::Invalid,
+    })
+}
+
+/// Converts an error from parsing a frame's header
+pub(crate) fn frame_error(e: ParseError) -> DecodingError {
+    match e {
+        ParseError::Incomplete(needed) => DecodingError::Incomplete { needed },
+        ParseError::Invalid | ParseError::Inconsistent | ParseError::ImpossibleTemperature(_) =>
+            DecodingError::BadHeader,
+    }
+}
+
+/// Converts an error from parsing a frame's data. The frame is already
+/// complete, so running out of input means the data was too short.
+pub(crate) fn data_error(data_type: DataType, e: ParseError) -> DecodingError {
+    match e {
+        ParseError::Incomplete(needed) => DecodingError::ShortData { needed },
+        ParseError::Invalid => DecodingError::InvalidData(data_type),
+        ParseError::Inconsistent => DecodingError::InconsistentData(data_type),
+        ParseError::ImpossibleTemperature(e) => DecodingError::ImpossibleTemperature(e),
+    }
+}
+
+/// Treats the input given to a streaming parser as final, turning
+/// `Incomplete` into `Truncated`
+pub(crate) fn complete<T>(result: DecodingResult<'_, T>) -> DecodingResult<'_, T> {
+    result.map_err(|e| match e {
+        DecodingError::Incomplete { needed } => DecodingError::Truncated { needed },
+        e => e,
+    })
+}
@@ -0,0 +1,267 @@
+use super::frame_data::*;
+use super::fault::FaultCode;
+use super::types::{Fan, Mode, Power, Setpoint, Temperature, TenthDegreesC};
+use crate::climate::{Action, Climate};
+
+/// Plays the part of the indoor unit: accepts a connection from a
+/// thermostat or WiFi adapter, applies its `SetRequest`s, and answers its
+/// polls from the state held here. The application keeps the state up to
+/// date, e.g. from a real unit when building a man-in-the-middle.
+///
+/// This does no I/O: decode each request received, pass it to `handle`, and
+/// send back the response, if any.
+///
+/// ```
+/// use mitsu_ac::protocol::{Device, Frame, FrameData, ConnectRequest, GetInfoRequest, Encodable};
+///
+/// let mut device = Device::new();
+///
+/// let response = device.handle(&FrameData::ConnectRequest(ConnectRequest)).unwrap();
+/// assert!(device.is_connected());
+///
+/// let response = device.handle(&FrameData::GetInfoRequest(GetInfoRequest::SETTINGS)).unwrap();
+/// let mut buf = [0u8; 22];
+/// Frame::from(response).encode(&mut buf).unwrap();
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Device {
+    pub settings: Settings,
+    pub room_temperature: TenthDegreesC,
+    pub compressor_frequency: u8,
+    pub operating: u8,
+    /// The `error_code` reported with faults; `0x8000` when there are none
+    pub error_code: u16,
+    pub fault: Option<FaultCode>,
+    connected: bool,
+}
+
+impl Device {
+    /// A disconnected device with `Settings::power_on_defaults`, a 22°C room
+    /// and no faults
+    pub fn new() -> Self {
+        Self {
+            settings: Settings::power_on_defaults(),
+            room_temperature: TenthDegreesC(220),
+            compressor_frequency: 0,
+            operating: 0,
+            error_code: 0x8000,
+            fault: None,
+            connected: false,
+        }
+    }
+
+    /// Whether a `ConnectRequest` has been received
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Forgets the connection, e.g. when the line has gone quiet. Until the
+    /// next `ConnectRequest`, everything else is ignored.
+    pub fn disconnect(&mut self) {
+        self.connected = false;
+    }
+
+    /// Handles a request, returning the response to send, if any. Nothing is
+    /// sent for requests received before connecting, for responses, or for
+    /// `InfoType`s whose responses aren't understood.
+    pub fn handle(&mut self, request: &FrameData) -> Option<FrameData> {
+        match request {
+            FrameData::ConnectRequest(_) => {
+                self.connected = true;
+                Some(FrameData::ConnectResponse(ConnectResponse::new(0)))
+            },
+            _ if !self.connected => None,
+            FrameData::SetRequest(request) => {
+                self.apply_request(request);
+                Some(FrameData::SetResponse(SetResponse))
+            },
+            FrameData::GetInfoRequest(request) =>
+                self.info(request.info_type()).map(FrameData::GetInfoResponse),
+            _ => None,
+        }
+    }
+
+    /// The response to a `GetInfoRequest` for `info_type`
+    pub fn info(&self, info_type: InfoType) -> Option<GetInfoResponse> {
+        match info_type {
+            InfoType::Settings => Some(GetInfoResponse::Settings(self.settings)),
+            InfoType::RoomTemp => Some(GetInfoResponse::RoomTemperature {
+                temperature: Temperature::HalfDegreesCPlusOffset {
+                    value: self.room_temperature.encode_as_half_deg_plus_offset(),
+                },
+            }),
+            InfoType::Type4 => Some(GetInfoResponse::Faults { error_code: self.error_code, fault: self.fault }),
+            InfoType::Status => Some(GetInfoResponse::Status {
+                compressor_frequency: self.compressor_frequency,
+                operating: self.operating,
+            }),
+            _ => None,
+        }
+    }
+
+    // As a unit does, a setpoint outside of the range of the mode it'll be
+    // in is ignored, while the rest of the request is still applied
+    fn apply_request(&mut self, request: &SetRequest) {
+        let settings = &mut self.settings;
+        if let Some(power) = request.power { settings.power = power; }
+        if let Some(mode) = request.mode { settings.mode = mode; }
+        if let Some(temp) = request.temp.filter(|temp| temp.is_valid_for(settings.mode)) { settings.setpoint = temp; }
+        if let Some(fan) = request.fan { settings.fan = fan; }
+        if let Some(vane) = request.vane { settings.vane = vane; }
+        if let Some(widevane) = request.widevane { settings.widevane = widevane; }
+    }
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Climate for Device {
+    type Error = core::convert::Infallible;
+
+    fn current_temperature(&self) -> Option<TenthDegreesC> {
+        Some(self.room_temperature)
+    }
+
+    fn target_temperature(&self) -> Option<Setpoint> {
+        Some(self.settings.setpoint)
+    }
+
+    fn power(&self) -> Option<Power> {
+        Some(self.settings.power)
+    }
+
+    fn mode(&self) -> Option<Mode> {
+        Some(self.settings.mode)
+    }
+
+    fn fan(&self) -> Option<Fan> {
+        Some(self.settings.fan)
+    }
+
+    /// See `Action::from_status`
+    fn action(&self) -> Action {
+        Action::from_status(&self.settings, Some(self.room_temperature), self.compressor_frequency, self.operating)
+    }
+
+    fn apply(&mut self, request: &SetRequest) -> Result<(), Self::Error> {
+        self.apply_request(request);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::frame::Frame;
+    use super::super::encoding::Encodable;
+
+    #[test]
+    fn ignores_requests_until_connected_test() {
+        let mut device = Device::new();
+        assert_eq!(None, device.handle(&FrameData::GetInfoRequest(GetInfoRequest::SETTINGS)));
+        assert!(device.handle(&FrameData::ConnectRequest(ConnectRequest)).is_some());
+        assert!(device.handle(&FrameData::GetInfoRequest(GetInfoRequest::SETTINGS)).is_some());
+        assert_eq!(None, device.handle(&FrameData::GetInfoRequest(GetInfoRequest::TIMERS)));
+
+        device.disconnect();
+        assert_eq!(None, device.handle(&FrameData::GetInfoRequest(GetInfoRequest::SETTINGS)));
+    }
+
+    #[test]
+    fn set_request_test() {
+        let mut device = Device::new();
+        device.handle(&FrameData::ConnectRequest(ConnectRequest));
+
+        let request = SetRequest {
+            power: Some(Power::On),
+            temp: Setpoint::new(TenthDegreesC(245)).ok(),
+            ..SetRequest::default()
+        };
+        assert_eq!(Some(FrameData::SetResponse(SetResponse)), device.handle(&FrameData::SetRequest(request)));
+        assert_eq!(Power::On, device.settings.power);
+        assert_eq!(TenthDegreesC(245), device.settings.setpoint.celsius_tenths());
+
+        let too_cold_to_cool = SetRequest {
+            mode: Some(Mode::Cool),
+            temp: Setpoint::new(TenthDegreesC(120)).ok(),
+            ..SetRequest::default()
+        };
+        device.handle(&FrameData::SetRequest(too_cold_to_cool));
+        assert_eq!(Mode::Cool, device.settings.mode);
+        assert_eq!(TenthDegreesC(245), device.settings.setpoint.celsius_tenths());
+    }
+
+    #[test]
+    fn climate_test() {
+        let mut device = Device::new();
+        assert_eq!(Action::Off, device.action());
+
+        device.set_power(Power::On).unwrap();
+        device.set_target_temperature(Setpoint::new(TenthDegreesC(240)).unwrap()).unwrap();
+        device.operating = 1;
+        assert_eq!(Action::Heating, device.action());
+
+        device.room_temperature = TenthDegreesC(250);
+        assert_eq!(Action::Cooling, device.action());
+
+        device.set_mode(Mode::Fan).unwrap();
+        device.operating = 0;
+        assert_eq!(Action::Fan, device.action());
+    }
+
+    #[test]
+    fn adjust_setpoint_test() {
+        let mut device = Device::new();
+        device.set_mode(Mode::Cool).unwrap();
+        device.set_target_temperature(Setpoint::new(TenthDegreesC(200)).unwrap()).unwrap();
+
+        assert_eq!(Ok(Some(Setpoint(TenthDegreesC(205)))), device.adjust_setpoint_by(TenthDegreesC(5)));
+        assert_eq!(Ok(Some(Setpoint(TenthDegreesC(210)))), device.adjust_setpoint_by(TenthDegreesC(1)));
+        assert_eq!(Ok(Some(Setpoint(TenthDegreesC(205)))), device.adjust_setpoint_by(TenthDegreesC(-1)));
+        assert_eq!(Ok(Some(Setpoint(TenthDegreesC(160)))), device.adjust_setpoint_by(TenthDegreesC(-100)));
+        assert_eq!(Setpoint(TenthDegreesC(160)), device.settings.setpoint);
+
+        device.set_mode(Mode::Heat).unwrap();
+        assert_eq!(Ok(Some(Setpoint(Setpoint::MIN))), device.adjust_setpoint_by(TenthDegreesC(i16::MIN)));
+    }
+
+    #[test]
+    fn encode_responses_test() {
+        let mut device = Device::new();
+        let mut buf = [0u8; 22];
+
+        let response = device.handle(&FrameData::ConnectRequest(ConnectRequest)).unwrap();
+        assert_eq!(Ok(7), Frame::from(response).encode(&mut buf[..7]));
+        assert_eq!([0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54], buf[..7]);
+
+        device.room_temperature = TenthDegreesC(215);
+        let response = device.handle(&FrameData::GetInfoRequest(GetInfoRequest::ROOM_TEMP)).unwrap();
+        assert_eq!(Ok(22), Frame::from(response).encode(&mut buf));
+        assert_eq!([0x03, 0x00, 0x00, 0x0b, 0x00, 0x00, 0xab], buf[5..12]);
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
+    fn responses_round_trip_test() {
+        let mut device = Device::new();
+        device.handle(&FrameData::ConnectRequest(ConnectRequest));
+        device.settings.isee = super::super::types::ISee::On;
+        device.room_temperature = TenthDegreesC(-35);
+        device.compressor_frequency = 42;
+        device.operating = 1;
+        device.error_code = 0x6840;
+        device.fault = Some(FaultCode::new(b'E', b'6'));
+
+        let mut buf = [0u8; 22];
+        for &info_type in InfoType::POLLED.iter() {
+            let response = device.handle(&FrameData::GetInfoRequest(GetInfoRequest::new(info_type))).unwrap();
+            Frame::from(response).encode(&mut buf).unwrap();
+
+            let (_, frame) = Frame::parse(&buf).unwrap();
+            assert_eq!(Ok(response), FrameData::parse_exact(frame));
+        }
+    }
+}
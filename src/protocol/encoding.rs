@@ -1,20 +1,35 @@
-#[derive(Debug, PartialEq, Eq)]
+use core::mem::MaybeUninit;
+use core::ops::Range;
+
+use super::reader::MAX_FRAME_LEN;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum EncodingError {
     BufferTooSmall,
     UnknownDataType,
     NotImplemented,
+    /// The data is longer than a frame's one-byte length field can describe
+    DataTooLong,
 }
 
+/// A type which always encodes to `LENGTH` bytes. Each type picks its own
+/// length, so a new packet type with a longer payload only needs a larger
+/// `LENGTH`; frames hold up to `MAX_DATA_LEN` bytes.
 pub trait FixedSizeEncoding {
     const LENGTH: usize;
 }
 
+/// A value whose encoded length is known before encoding it, which may vary
+/// from value to value. Implemented for every `FixedSizeEncoding`, and for
+/// byte slices.
 pub trait SizedEncoding {
     fn length(&self) -> usize;
 }
 
+/// A value which can be encoded into a buffer of exactly `length()` bytes,
+/// returning the number of bytes written
 pub trait Encodable : SizedEncoding {
-    fn encode<'a>(&self, into: &'a mut [u8]) -> Result<usize, EncodingError>;
+    fn encode(&self, into: &mut [u8]) -> Result<usize, EncodingError>;
 }
 
 impl<T> SizedEncoding for T where T: FixedSizeEncoding {
@@ -26,7 +41,7 @@ macro_rules! one_byte_encodable_enum {
     ( $( $enum:ty ),* ) => {
         $(
             impl Encodable for $enum where $enum: OneByteEncodable {
-                fn encode<'a>(&self, into: &'a mut [u8]) -> Result<usize, EncodingError> {
+                fn encode(&self, into: &mut [u8]) -> Result<usize, EncodingError> {
                     if into.len() != 1 { return Err(EncodingError::BufferTooSmall); }
                     into[0] = self.encoded_as_byte();
                     Ok(1)
@@ -49,7 +64,7 @@ impl<T> FixedSizeEncoding for Option<T> where T: FixedSizeEncoding {
 }
 
 impl<T> Encodable for Option<T> where T: Encodable + FixedSizeEncoding {
-    fn encode<'a>(&self, into: &'a mut [u8]) -> Result<usize, EncodingError> {
+    fn encode(&self, into: &mut [u8]) -> Result<usize, EncodingError> {
         match self {
             Some(encodable) => encodable.encode(into),
             None => Ok(0)
@@ -62,9 +77,261 @@ impl SizedEncoding for &[u8] {
 }
 
 impl Encodable for &[u8] {
-    fn encode<'a>(&self, into: &'a mut [u8]) -> Result<usize, EncodingError> {
+    fn encode(&self, into: &mut [u8]) -> Result<usize, EncodingError> {
         if into.len() != self.len() { return Err(EncodingError::BufferTooSmall); }
         into.copy_from_slice(self);
         Ok(self.len())
     }
 }
+
+/// Encodes `items` back-to-back into `buf`, such as all of the frames for one
+/// poll cycle so they can be sent in a single DMA transfer. The range of `buf`
+/// holding each item is written to the corresponding entry of `ranges`, and
+/// the total length encoded is returned.
+///
+/// Fails with `BufferTooSmall` if either `buf` or `ranges` is too short.
+///
+/// ```
+/// use mitsu_ac::protocol::{encode_all, Frame, DataType};
+///
+/// let frames = [
+///     Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..]),
+///     Frame::new(DataType::GetInfoRequest, &[0x02][..]),
+/// ];
+/// let mut buf = [0u8; 32];
+/// let mut ranges = [0..0, 0..0];
+///
+/// let len = encode_all(&frames, &mut buf, &mut ranges).unwrap();
+///
+/// assert_eq!(15, len);
+/// assert_eq!([0..8, 8..15], ranges);
+/// ```
+pub fn encode_all<T: Encodable>(
+    items: &[T],
+    buf: &mut [u8],
+    ranges: &mut [Range<usize>],
+) -> Result<usize, EncodingError> {
+    if ranges.len() < items.len() {
+        return Err(EncodingError::BufferTooSmall);
+    }
+
+    let mut cursor = EncodeBuffer::new(buf);
+    for (item, range) in items.iter().zip(ranges.iter_mut()) {
+        *range = cursor.push(item)?;
+    }
+
+    Ok(cursor.len())
+}
+
+/// Encodes `item` into the front of an uninitialised buffer, such as a DMA
+/// transmit descriptor, returning the part of it written. `Encodable` only
+/// writes to initialised bytes, so the `item.length()` bytes the item takes
+/// up are still zeroed before it's encoded into them; the rest of the buffer
+/// is left untouched.
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use mitsu_ac::protocol::{encode_uninit, Frame, DataType};
+///
+/// let mut descriptor = [MaybeUninit::<u8>::uninit(); 32];
+/// let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..]);
+///
+/// let encoded = encode_uninit(&frame, &mut descriptor).unwrap();
+/// assert_eq!(&[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], encoded);
+/// ```
+pub fn encode_uninit<'b, T: Encodable>(item: &T, buf: &'b mut [MaybeUninit<u8>]) -> Result<&'b mut [u8], EncodingError> {
+    let out = buf.get_mut(..item.length()).ok_or(EncodingError::BufferTooSmall)?;
+    for byte in out.iter_mut() {
+        byte.write(0);
+    }
+    // SAFETY: every byte of `out` has just been written, and `MaybeUninit<u8>`
+    // has the same layout as `u8`
+    let out = unsafe { core::slice::from_raw_parts_mut(out.as_mut_ptr() as *mut u8, out.len()) };
+    let len = item.encode(out)?;
+    Ok(&mut out[..len])
+}
+
+/// A cursor over a buffer, encoding items one after another without the
+/// caller keeping track of where the last one ended
+///
+/// ```
+/// use mitsu_ac::protocol::{EncodeBuffer, Frame, DataType};
+///
+/// let mut buf = [0u8; 32];
+/// let mut tx = EncodeBuffer::new(&mut buf);
+///
+/// tx.push(&Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..])).unwrap();
+/// tx.push(&Frame::new(DataType::GetInfoRequest, &[0x02][..])).unwrap();
+///
+/// assert_eq!(15, tx.len());
+/// assert_eq!(&[0xfc, 0x5a], &tx.as_slice()[..2]);
+/// ```
+#[derive(Debug)]
+pub struct EncodeBuffer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> EncodeBuffer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Encodes `item` after everything pushed so far, returning the range of
+    /// the buffer it was written to. Nothing is kept if it fails.
+    pub fn push<T: Encodable>(&mut self, item: &T) -> Result<Range<usize>, EncodingError> {
+        let start = self.len;
+        let into = self.buf.get_mut(start..start + item.length()).ok_or(EncodingError::BufferTooSmall)?;
+        self.len += item.encode(into)?;
+        Ok(start..self.len)
+    }
+
+    /// The number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The space left in the buffer
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.len
+    }
+
+    /// The bytes written so far
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Starts writing from the start of the buffer again
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// Somewhere to write encoded bytes, such as a UART's transmit queue, so
+/// frames can be encoded straight into it
+///
+/// ```
+/// use mitsu_ac::protocol::{ByteSink, Frame, DataType};
+/// use mitsu_ac::protocol::encoding::EncodingError;
+///
+/// /// A transmit queue with room for one frame
+/// struct Tx { queued: usize }
+///
+/// impl ByteSink for Tx {
+///     fn push(&mut self, _byte: u8) -> Result<(), EncodingError> {
+///         if self.queued == 8 { return Err(EncodingError::BufferTooSmall); }
+///         self.queued += 1;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut tx = Tx { queued: 0 };
+/// assert_eq!(Ok(8), tx.push_encoded(&Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..])));
+/// ```
+pub trait ByteSink {
+    /// Writes one byte, failing with `BufferTooSmall` if there's no room
+    fn push(&mut self, byte: u8) -> Result<(), EncodingError>;
+
+    /// Writes each of `bytes` in turn
+    fn push_slice(&mut self, bytes: &[u8]) -> Result<(), EncodingError> {
+        bytes.iter().try_for_each(|&byte| self.push(byte))
+    }
+
+    /// Encodes `item` and writes it, returning its length. The sinks in this
+    /// crate encode straight into their own storage; by default, `item` is
+    /// encoded into a buffer on the stack first, so items longer than
+    /// `MAX_FRAME_LEN` can't be written.
+    fn push_encoded<T: Encodable>(&mut self, item: &T) -> Result<usize, EncodingError> where Self: Sized {
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        let into = buf.get_mut(..item.length()).ok_or(EncodingError::BufferTooSmall)?;
+        let len = item.encode(into)?;
+        self.push_slice(&buf[..len])?;
+        Ok(len)
+    }
+}
+
+/// Writes to the front of the slice, leaving `self` as the unwritten rest,
+/// as `std::io::Write` does
+impl ByteSink for &mut [u8] {
+    fn push(&mut self, byte: u8) -> Result<(), EncodingError> {
+        self.push_slice(&[byte])
+    }
+
+    fn push_slice(&mut self, bytes: &[u8]) -> Result<(), EncodingError> {
+        if self.len() < bytes.len() {
+            return Err(EncodingError::BufferTooSmall);
+        }
+        let (written, rest) = core::mem::take(self).split_at_mut(bytes.len());
+        written.copy_from_slice(bytes);
+        *self = rest;
+        Ok(())
+    }
+
+    fn push_encoded<T: Encodable>(&mut self, item: &T) -> Result<usize, EncodingError> {
+        let into = self.get_mut(..item.length()).ok_or(EncodingError::BufferTooSmall)?;
+        let len = item.encode(into)?;
+        *self = &mut core::mem::take(self)[len..];
+        Ok(len)
+    }
+}
+
+impl ByteSink for EncodeBuffer<'_> {
+    fn push(&mut self, byte: u8) -> Result<(), EncodingError> {
+        self.push_slice(&[byte])
+    }
+
+    fn push_slice(&mut self, bytes: &[u8]) -> Result<(), EncodingError> {
+        let into = self.buf.get_mut(self.len..self.len + bytes.len()).ok_or(EncodingError::BufferTooSmall)?;
+        into.copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    fn push_encoded<T: Encodable>(&mut self, item: &T) -> Result<usize, EncodingError> {
+        EncodeBuffer::push(self, item).map(|range| range.len())
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> ByteSink for heapless::Vec<u8, N> {
+    fn push(&mut self, byte: u8) -> Result<(), EncodingError> {
+        heapless::Vec::push(self, byte).map_err(|_| EncodingError::BufferTooSmall)
+    }
+
+    fn push_slice(&mut self, bytes: &[u8]) -> Result<(), EncodingError> {
+        self.extend_from_slice(bytes).map_err(|_| EncodingError::BufferTooSmall)
+    }
+
+    fn push_encoded<T: Encodable>(&mut self, item: &T) -> Result<usize, EncodingError> {
+        let start = self.len();
+        self.resize(start + item.length(), 0).map_err(|_| EncodingError::BufferTooSmall)?;
+        let result = item.encode(&mut self[start..]);
+        self.truncate(start + *result.as_ref().unwrap_or(&0));
+        result
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSink for alloc::vec::Vec<u8> {
+    fn push(&mut self, byte: u8) -> Result<(), EncodingError> {
+        alloc::vec::Vec::push(self, byte);
+        Ok(())
+    }
+
+    fn push_slice(&mut self, bytes: &[u8]) -> Result<(), EncodingError> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn push_encoded<T: Encodable>(&mut self, item: &T) -> Result<usize, EncodingError> {
+        let start = self.len();
+        self.resize(start + item.length(), 0);
+        let result = item.encode(&mut self[start..]);
+        self.truncate(start + *result.as_ref().unwrap_or(&0));
+        result
+    }
+}
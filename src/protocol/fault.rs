@@ -0,0 +1,110 @@
+/// A Mitsubishi fault code, as shown on the remote or indoor unit display
+/// (`E6`, `P8`, `U4`, ...)
+///
+/// ```
+/// use mitsu_ac::protocol::fault::FaultCode;
+///
+/// let fault = FaultCode::from_short_code(0x46).unwrap();
+/// assert_eq!(fault.as_str(), "E6");
+/// assert_eq!(fault.description(), Some("Indoor/outdoor unit communication error"));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FaultCode([u8; 2]);
+
+// Alphabets used by the one-byte "short code" in the Type4 response: the top
+// three bits select the letter, the bottom five the second character.
+const LETTERS: &[u8] = b"AbEFJLPU";
+const DIGITS: &[u8] = b"0123456789ABCDEFOHJLPU";
+
+/// Known fault codes with short descriptions. These are summaries; refer to
+/// the unit's service manual for the specifics of a given model.
+const KNOWN_FAULTS: &[(&str, &str)] = &[
+    ("E0", "Remote controller transmission error"),
+    ("E3", "Remote controller transmission error"),
+    ("E4", "Remote controller reception error"),
+    ("E5", "Remote controller reception error"),
+    ("E6", "Indoor/outdoor unit communication error"),
+    ("E7", "Indoor/outdoor unit communication error"),
+    ("E8", "Indoor/outdoor unit communication error (indoor receiving)"),
+    ("E9", "Indoor/outdoor unit communication error (outdoor receiving)"),
+    ("P1", "Intake air temperature sensor error"),
+    ("P2", "Pipe (liquid) temperature sensor error"),
+    ("P4", "Drain float switch error"),
+    ("P5", "Drain pump error"),
+    ("P6", "Freezing/overheating protection"),
+    ("P8", "Pipe temperature error"),
+    ("P9", "Pipe (two-phase) temperature sensor error"),
+    ("U1", "Abnormal high pressure"),
+    ("U2", "Abnormal discharge temperature or low refrigerant"),
+    ("U3", "Outdoor discharge thermistor open/short"),
+    ("U4", "Outdoor thermistor open/short"),
+    ("U5", "Outdoor heatsink temperature abnormal"),
+    ("U6", "Compressor overcurrent interruption"),
+    ("U8", "Outdoor fan motor error"),
+    ("U9", "Outdoor unit power supply error"),
+    ("UP", "Compressor overcurrent interruption"),
+];
+
+impl FaultCode {
+    /// Builds a fault code from its two display characters, e.g. `b'E', b'6'`
+    pub fn new(letter: u8, number: u8) -> Self {
+        FaultCode([letter, number])
+    }
+
+    /// Decodes the one-byte short code reported by the unit. `0x00` means no
+    /// fault.
+    pub fn from_short_code(byte: u8) -> Option<Self> {
+        if byte == 0 { return None; }
+
+        let letter = LETTERS.get((byte >> 5) as usize)?;
+        let number = DIGITS.get((byte & 0x1f) as usize)?;
+        Some(Self::new(*letter, *number))
+    }
+
+    /// Encodes the code as the one-byte short code used by the unit, if it
+    /// can be represented. `A0` can't be, as its short code means no fault.
+    pub fn short_code(&self) -> Option<u8> {
+        let letter = LETTERS.iter().position(|&b| b == self.0[0])?;
+        let number = DIGITS.iter().position(|&b| b == self.0[1])?;
+        match ((letter as u8) << 5) | number as u8 {
+            0 => None,
+            byte => Some(byte),
+        }
+    }
+
+    /// The code as displayed, e.g. `"E6"`
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.0).unwrap_or("??")
+    }
+
+    /// A short human-readable description, if this is a known code
+    pub fn description(&self) -> Option<&'static str> {
+        KNOWN_FAULTS.iter()
+            .find(|(code, _)| *code == self.as_str())
+            .map(|(_, description)| *description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_code_test() {
+        assert_eq!(None, FaultCode::from_short_code(0x00));
+        assert_eq!(Some(FaultCode::new(b'P', b'8')), FaultCode::from_short_code(0xc8));
+        assert_eq!(Some(FaultCode::new(b'U', b'4')), FaultCode::from_short_code(0xe4));
+        assert_eq!(None, FaultCode::from_short_code(0xff));
+
+        assert_eq!(Some(0xc8), FaultCode::new(b'P', b'8').short_code());
+        assert_eq!(Some(0x46), FaultCode::new(b'E', b'6').short_code());
+        assert_eq!(None, FaultCode::new(b'A', b'0').short_code());
+        assert_eq!(None, FaultCode::new(b'Z', b'1').short_code());
+    }
+
+    #[test]
+    fn description_test() {
+        assert_eq!(Some("Pipe temperature error"), FaultCode::new(b'P', b'8').description());
+        assert_eq!(None, FaultCode::new(b'A', b'0').description());
+    }
+}
@@ -1,70 +1,166 @@
+use core::convert::TryFrom;
+#[cfg(feature = "nom")]
 use nom::number::streaming::be_u8;
+#[cfg(feature = "nom")]
 use nom::do_parse;
 
 use super::encoding::{Encodable, EncodingError, SizedEncoding};
+use super::decoding::{self, DecodingError, DecodingResult, UnknownValue};
 
 /// The type of data contained in a frame. We don't know all of the possible
-/// types, just a few that have been reverse-engineered.
-#[repr(u8)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// types, just a few that have been reverse-engineered; the byte of any other
+/// is kept in `Unknown`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum DataType {
-    SetRequest = 0x41,
-    GetInfoRequest = 0x42,
-    ConnectRequest = 0x5a,
+    SetRequest,
+    GetInfoRequest,
+    ConnectRequest,
 
-    SetResponse = 0x61,
-    GetInfoResponse = 0x62,
-    ConnectResponse = 0x7a,
+    SetResponse,
+    GetInfoResponse,
+    ConnectResponse,
 
-    Unknown = 0xff,
+    Unknown(u8),
 }
 
-impl From<u8> for DataType {
-    fn from(byte: u8) -> Self {
+impl DataType {
+    /// Decodes a byte, treating any type we don't know about as `Unknown`
+    pub fn from_byte(byte: u8) -> Self {
+        Self::try_from(byte).unwrap_or(DataType::Unknown(byte))
+    }
+
+    /// The byte the data type is encoded as
+    pub const fn as_byte(&self) -> u8 {
+        match *self {
+            DataType::SetRequest => 0x41,
+            DataType::GetInfoRequest => 0x42,
+            DataType::ConnectRequest => 0x5a,
+
+            DataType::SetResponse => 0x61,
+            DataType::GetInfoResponse => 0x62,
+            DataType::ConnectResponse => 0x7a,
+
+            DataType::Unknown(byte) => byte,
+        }
+    }
+}
+
+impl TryFrom<u8> for DataType {
+    type Error = UnknownValue;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
         match byte {
-            0x41 => DataType::SetRequest,
-            0x42 => DataType::GetInfoRequest,
-            0x5a => DataType::ConnectRequest,
+            0x41 => Ok(DataType::SetRequest),
+            0x42 => Ok(DataType::GetInfoRequest),
+            0x5a => Ok(DataType::ConnectRequest),
 
-            0x61 => DataType::SetResponse,
-            0x62 => DataType::GetInfoResponse,
-            0x7a => DataType::ConnectResponse,
+            0x61 => Ok(DataType::SetResponse),
+            0x62 => Ok(DataType::GetInfoResponse),
+            0x7a => Ok(DataType::ConnectResponse),
 
-            _ => DataType::Unknown,
+            _ => Err(UnknownValue { type_name: "DataType", byte }),
         }
     }
 }
 
-const FRAME_START: u8 = 0xfc;
-const FRAME_B3: u8 = 0x01;
-const FRAME_B4: u8 = 0x30;
+pub(super) const FRAME_START: u8 = 0xfc;
+pub(crate) const HEADER_LEN: usize = 5;
+
+/// The longest data a frame can hold, as its length is a single byte. Units
+/// seen so far never send more than 16 bytes, but frames up to this length
+/// are encoded, and can be parsed with `Frame::parse_with_limit`.
+pub const MAX_DATA_LEN: usize = 255;
+
+/// The longest data `Frame::parse` accepts. A longer declared length is far
+/// more likely to be a corrupted length byte than a real frame, and waiting
+/// for the data would hold up every frame behind it.
+pub const DEFAULT_MAX_DATA_LEN: usize = 0x20;
+
+/// The two fixed bytes between a frame's data type and its length. Every
+/// CN105 unit seen so far uses `0x01 0x30`, but adapters for some other unit
+/// families (PAC, PEA, City Multi) are reported to use other values.
+///
+/// ```
+/// use mitsu_ac::protocol::{Frame, DataType, Header};
+///
+/// let header = Header::new(0x02, 0x30);
+/// let (_, frame) = Frame::parse_with_header(&[0xfc, 0x7a, 0x02, 0x30, 0x01, 0x00, 0x53], header).unwrap();
+/// assert_eq!(header, frame.header);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Header {
+    pub b3: u8,
+    pub b4: u8,
+}
+
+impl Header {
+    /// The header bytes used by CN105 units
+    pub const CN105: Header = Header { b3: 0x01, b4: 0x30 };
+
+    pub const fn new(b3: u8, b4: u8) -> Self {
+        Self { b3, b4 }
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header::CN105
+    }
+}
 
 /// A single protocol frame, mainly here to identify and wrap some data.
 /// Generally used as either `Frame<&[u8]>` in the case of a frame that's just
 /// been parsed from a byte slice, or as `Frame<FrameData>` for a frame that
 /// is being built up to be encoded into a byte slice.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Frame<T: Encodable> {
     pub data_type: DataType,
-    pub data_len: usize,
+    pub header: Header,
     pub data: T,
 }
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum FrameParsingError<'a> {
-    InvalidChecksum,
-    IncompleteData(Option<usize>),
-    UnknownError(&'a [u8]),
-}
-
 impl<T> Frame<T> where T: Encodable {
-    pub fn new(data_type: DataType, data_len: usize, data: T) -> Self {
+    /// Builds a frame with the CN105 header bytes. Its length is always that
+    /// of `data`.
+    pub fn new(data_type: DataType, data: T) -> Self {
         Self {
             data_type,
-            data_len,
+            header: Header::CN105,
             data,
         }
     }
+
+    /// Replaces the frame's header bytes
+    pub fn with_header(self, header: Header) -> Self {
+        Self { header, ..self }
+    }
+
+    /// The frame's payload
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// Consumes the frame, returning its payload
+    pub fn into_data(self) -> T {
+        self.data
+    }
+
+    /// Converts the frame's payload, keeping its data type
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::{Frame, DataType, FrameData, ConnectRequest};
+    ///
+    /// let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..]);
+    /// let frame = frame.map_data(|_| FrameData::ConnectRequest(ConnectRequest));
+    /// assert_eq!(frame.data(), &FrameData::ConnectRequest(ConnectRequest));
+    /// ```
+    pub fn map_data<U, F>(self, f: F) -> Frame<U> where U: Encodable, F: FnOnce(T) -> U {
+        Frame {
+            data_type: self.data_type,
+            header: self.header,
+            data: f(self.data),
+        }
+    }
 }
 
 /// A frame parsed from a byte slice
@@ -76,65 +172,341 @@ impl<T> Frame<T> where T: Encodable {
 ///
 /// if let Ok((_, frame)) = Frame::parse(&buf) {
 ///     assert_eq!(frame.data_type, DataType::ConnectRequest);
-///     assert_eq!(frame.data_len, 0x02);
 ///     assert_eq!(frame.data, &[0xca, 0x01][0..2]);
 /// }
 /// ```
 impl Frame<&[u8]> {
-    pub fn parse_until<'a>(data: &'a [u8]) -> nom::IResult<&'a [u8], &'a [u8]> {
-        take_till!(data, |b| b == FRAME_START)
+    /// Skips input up to the start of the next frame, returning the skipped
+    /// bytes
+    pub fn parse_until(data: &[u8]) -> DecodingResult<'_, &[u8]> {
+        Self::skip_to_start(data).map_err(decoding::frame_error)
+    }
+
+    /// Parses a frame with the CN105 header bytes
+    pub fn parse(data: &[u8]) -> DecodingResult<'_, Frame<&[u8]>> {
+        Self::parse_with_header(data, Header::CN105)
+    }
+
+    /// Parses a frame as `parse` does, but treating `data` as all the input
+    /// there is, e.g. when replaying a log. Running out of input is
+    /// reported as `Truncated` rather than `Incomplete`.
+    pub fn parse_complete(data: &[u8]) -> DecodingResult<'_, Frame<&[u8]>> {
+        decoding::complete(Self::parse(data))
+    }
+
+    /// Parses a frame, expecting the given header bytes
+    pub fn parse_with_header(data: &[u8], header: Header) -> DecodingResult<'_, Frame<&[u8]>> {
+        Self::parse_with::<SumChecksum>(data, header)
+    }
+
+    /// Parses a frame, expecting the given header bytes, and rejecting
+    /// frames declaring more than `max_data_len` bytes of data with
+    /// `BadHeader` as soon as the length byte arrives. `parse` and the other
+    /// methods use `DEFAULT_MAX_DATA_LEN`.
+    pub fn parse_with_limit(data: &[u8], header: Header, max_data_len: usize) -> DecodingResult<'_, Frame<&[u8]>> {
+        Self::parse_checked::<SumChecksum>(data, header, max_data_len)
+    }
+
+    /// Parses a frame, expecting the given header bytes and checking its
+    /// checksum byte with `C`
+    pub fn parse_with<C: Checksum>(data: &[u8], header: Header) -> DecodingResult<'_, Frame<&[u8]>> {
+        Self::parse_checked::<C>(data, header, DEFAULT_MAX_DATA_LEN)
+    }
+
+    fn parse_checked<C: Checksum>(input: &[u8], header: Header, max_data_len: usize) -> DecodingResult<'_, Frame<&[u8]>> {
+        let declared_len = input.get(HEADER_LEN - 1).map(|&len| len as usize);
+        if input.first() == Some(&FRAME_START) && declared_len.is_some_and(|len| len > max_data_len) {
+            return Err(DecodingError::BadHeader);
+        }
+
+        let (rest, (data_type, data_len, data)) = Self::parse_header_and_data(input, header)
+            .map_err(decoding::frame_error)?;
+
+        match rest.split_first() {
+            Some((&received, rest)) => {
+                // Over the bytes received, as an unknown data type's byte
+                // doesn't survive decoding
+                let calculated = C::of(&[&input[..HEADER_LEN + data_len]]);
+                if received == calculated {
+                    Ok((rest, Frame::new(data_type, data).with_header(header)))
+                } else {
+                    Err(DecodingError::InvalidChecksum { data_type, data_len, calculated, received })
+                }
+            },
+            None => Err(DecodingError::Incomplete { needed: Some(1) }),
+        }
+    }
+
+    /// Finds and parses the first valid frame in `data`, skipping junk
+    /// before it and any start bytes which don't begin a valid frame.
+    /// Returns the number of bytes which can be discarded along with the
+    /// result: on success, everything up to the end of the frame; otherwise,
+    /// everything before the first frame which is still incomplete.
+    ///
+    /// The search stops at an incomplete frame, so `Err(Incomplete)` means
+    /// more input is needed. If no start byte is found at all, every byte is
+    /// discarded and `needed` is `None`.
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::{Frame, DataType};
+    ///
+    /// // Junk, a corrupted frame, then a connect request
+    /// let buf: &[u8] = &[0x00, 0xfc, 0x5a, 0xff, 0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8, 0xfc];
+    ///
+    /// let (consumed, frame) = Frame::scan(buf);
+    /// assert_eq!(12, consumed);
+    /// assert_eq!(DataType::ConnectRequest, frame.unwrap().data_type);
+    /// ```
+    pub fn scan(data: &[u8]) -> (usize, Result<Frame<&[u8]>, DecodingError>) {
+        Self::scan_with_header(data, Header::CN105)
+    }
+
+    /// Scans for a frame, as `scan` does, expecting the given header bytes
+    pub fn scan_with_header(data: &[u8], header: Header) -> (usize, Result<Frame<&[u8]>, DecodingError>) {
+        let (consumed, result, _) = Self::scan_with_stats(data, header);
+        (consumed, result)
+    }
+
+    /// Scans for a frame, as `scan_with_header` does, also reporting what was
+    /// passed over on the way, e.g. for `Stats::record_scan`
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::{Frame, Header, ScanStats};
+    ///
+    /// let buf: &[u8] = &[0x00, 0xfc, 0x5a, 0xff, 0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8];
+    /// let (_, frame, stats) = Frame::scan_with_stats(buf, Header::CN105);
+    ///
+    /// assert!(frame.is_ok());
+    /// assert_eq!(ScanStats { skipped: 4, resyncs: 1, checksum_failures: 0 }, stats);
+    /// ```
+    pub fn scan_with_stats(data: &[u8], header: Header) -> (usize, Result<Frame<&[u8]>, DecodingError>, ScanStats) {
+        let mut stats = ScanStats::default();
+        let mut start = 0;
+        while let Some(offset) = data[start..].iter().position(|&b| b == FRAME_START) {
+            start += offset;
+            stats.skipped = start;
+            match Self::parse_with_header(&data[start..], header) {
+                Ok((rest, frame)) => return (data.len() - rest.len(), Ok(frame), stats),
+                Err(e @ DecodingError::Incomplete { .. }) => return (start, Err(e), stats),
+                Err(e) => {
+                    match e {
+                        DecodingError::InvalidChecksum { .. } => stats.checksum_failures += 1,
+                        _ => stats.resyncs += 1,
+                    }
+                    start += 1;
+                },
+            }
+        }
+        stats.skipped = data.len();
+        (data.len(), Err(DecodingError::Incomplete { needed: None }), stats)
+    }
+
+    #[cfg(feature = "nom")]
+    fn skip_to_start(data: &[u8]) -> decoding::ParseResult<'_, &[u8]> {
+        decoding::from_nom(take_till!(data, |b| b == FRAME_START))
     }
 
-    pub fn parse<'a>(data: &'a [u8]) -> nom::IResult<&'a [u8], Frame<&'a [u8]>> {
-        do_parse!(data,
+    #[cfg(feature = "nom")]
+    fn parse_header_and_data(data: &[u8], header: Header) -> decoding::ParseResult<'_, (DataType, usize, &[u8])> {
+        decoding::from_nom(do_parse!(data,
             tag!(&[FRAME_START]) >>
-            data_type: map!(be_u8, DataType::from) >>
-            tag!(&[FRAME_B3, FRAME_B4]) >>
+            data_type: map!(be_u8, DataType::from_byte) >>
+            tag!(&[header.b3, header.b4]) >>
             data_len: map!(be_u8, |b| b as usize) >>
             data: take!(data_len) >>
-            frame: value!(Frame::new(data_type, data_len, data)) >>
-            verify!(be_u8, |b| *b == checksum(data_type, data_len, data)) >>
-            (frame)
-        )
+            ((data_type, data_len, data))
+        ))
+    }
+}
+
+/// What `Frame::scan_with_stats` passed over before its result
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ScanStats {
+    /// Bytes discarded before the frame found, or before the incomplete frame
+    /// which stopped the scan
+    pub skipped: usize,
+    /// Frame start bytes skipped as they were followed by a bad header
+    pub resyncs: u32,
+    /// Frames skipped as their checksum didn't match
+    pub checksum_failures: u32,
+}
+
+/// Calculates the checksum byte for a CN105 frame with the given data type
+/// and data
+///
+/// ```
+/// use mitsu_ac::protocol::{checksum, DataType};
+///
+/// assert_eq!(0xa8, checksum(DataType::ConnectRequest, &[0xca, 0x01]));
+/// ```
+pub fn checksum(data_type: DataType, data: &[u8]) -> u8 {
+    checksum_with_header(Header::CN105, data_type, data)
+}
+
+/// Calculates the checksum byte for a frame with the given header bytes, data
+/// type and data
+pub fn checksum_with_header(header: Header, data_type: DataType, data: &[u8]) -> u8 {
+    frame_checksum::<SumChecksum>(header, data_type, data)
+}
+
+fn frame_checksum<C: Checksum>(header: Header, data_type: DataType, data: &[u8]) -> u8 {
+    let header = [FRAME_START, data_type.as_byte(), header.b3, header.b4, data.len() as u8];
+    C::of(&[&header, data])
+}
+
+/// A frame checksum algorithm, run over every byte of a frame before the
+/// checksum byte itself. CN105 units use `SumChecksum`; other Mitsubishi
+/// serial links built on the same framing can plug in their own.
+///
+/// ```
+/// use mitsu_ac::protocol::{Checksum, Frame, DataType, Header, Encodable};
+///
+/// /// An XOR of every byte
+/// #[derive(Default)]
+/// struct Xor(u8);
+///
+/// impl Checksum for Xor {
+///     fn update(&mut self, bytes: &[u8]) {
+///         self.0 = bytes.iter().fold(self.0, |acc, b| acc ^ b);
+///     }
+///
+///     fn finish(&self) -> u8 {
+///         self.0
+///     }
+/// }
+///
+/// let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..]);
+/// let mut buf = [0u8; 8];
+/// frame.encode_with::<Xor>(&mut buf).unwrap();
+///
+/// assert_eq!(Ok((&[][..], frame)), Frame::parse_with::<Xor>(&buf, Header::CN105));
+/// ```
+pub trait Checksum: Default {
+    /// Adds more of the frame's bytes
+    fn update(&mut self, bytes: &[u8]);
+
+    /// The checksum byte of all of the bytes added
+    fn finish(&self) -> u8;
+
+    /// The checksum byte of `parts`, taken in order
+    fn of(parts: &[&[u8]]) -> u8 {
+        let mut checksum = Self::default();
+        for part in parts {
+            checksum.update(part);
+        }
+        checksum.finish()
+    }
+}
+
+/// The CN105 checksum: `0xfc` minus the sum of every byte, modulo 256
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SumChecksum {
+    sum: u8,
+}
+
+impl Checksum for SumChecksum {
+    fn update(&mut self, bytes: &[u8]) {
+        self.sum = bytes.iter().fold(self.sum, |acc, b| acc.wrapping_add(*b));
+    }
+
+    fn finish(&self) -> u8 {
+        0xfcu8.wrapping_sub(self.sum)
     }
 }
 
-fn checksum(data_type: DataType, data_len: usize, data: &[u8]) -> u8 {
-    let header_sum = FRAME_START as u32
-        + data_type as u32
-        + FRAME_B3 as u32
-        + FRAME_B4 as u32
-        + data_len as u32;
-    let sum = data.iter().fold(header_sum, |acc, b| acc + *b as u32);
-    0xfc - (sum as u8)
+/// Recalculates the trailing checksum byte of a raw, encoded frame from the
+/// rest of its bytes and writes it, e.g. after editing a captured frame in
+/// place. As with `verify_checksum`, nothing else is validated.
+///
+/// ```
+/// use mitsu_ac::protocol::{fix_checksum, verify_checksum};
+///
+/// let mut frame = [0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8];
+/// frame[6] = 0x02;
+///
+/// fix_checksum(&mut frame).unwrap();
+/// assert!(verify_checksum(&frame).is_ok());
+/// ```
+pub fn fix_checksum(frame: &mut [u8]) -> Result<(), DecodingError> {
+    if frame.len() < HEADER_LEN + 1 {
+        return Err(DecodingError::Incomplete { needed: Some(HEADER_LEN + 1 - frame.len()) });
+    }
+
+    let (contents, checksum) = frame.split_at_mut(frame.len() - 1);
+    checksum[0] = SumChecksum::of(&[contents]);
+    Ok(())
+}
+
+/// Checks the trailing checksum byte of a raw, encoded frame against the rest
+/// of its bytes. Nothing but the checksum is validated, so this is also useful
+/// on frames which have been edited by hand.
+///
+/// ```
+/// use mitsu_ac::protocol::verify_checksum;
+///
+/// assert!(verify_checksum(&[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8]).is_ok());
+/// assert!(verify_checksum(&[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x02, 0xa8]).is_err());
+/// ```
+pub fn verify_checksum(frame: &[u8]) -> Result<(), DecodingError> {
+    if frame.len() < HEADER_LEN + 1 {
+        return Err(DecodingError::Incomplete { needed: Some(HEADER_LEN + 1 - frame.len()) });
+    }
+
+    let (contents, received) = frame.split_at(frame.len() - 1);
+    let received = received[0];
+    let calculated = SumChecksum::of(&[contents]);
+
+    if calculated == received {
+        Ok(())
+    } else {
+        Err(DecodingError::InvalidChecksum {
+            data_type: DataType::from_byte(contents[1]),
+            data_len: contents[4] as usize,
+            calculated,
+            received,
+        })
+    }
 }
 
 impl<T> SizedEncoding for Frame<T> where T: Encodable {
     fn length(&self) -> usize {
-        5 + self.data.length() + 1
+        HEADER_LEN + self.data.length() + 1
     }
 }
 
 impl<T> Encodable for Frame<T> where T: Encodable {
     fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
-        if buf.len() < 5 + self.data_len + 1 {
+        self.encode_with::<SumChecksum>(buf)
+    }
+}
+
+impl<T> Frame<T> where T: Encodable {
+    /// Encodes the frame as `Encodable::encode` does, calculating its
+    /// checksum byte with `C`
+    pub fn encode_with<C: Checksum>(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
+        if self.data.length() > MAX_DATA_LEN {
+            return Err(EncodingError::DataTooLong);
+        }
+
+        let length = self.length();
+        if buf.len() < length {
             return Err(EncodingError::BufferTooSmall);
         }
 
-        let (header, rest): (&mut [u8], &mut [u8]) = buf.split_at_mut(5);
+        let (header, rest): (&mut [u8], &mut [u8]) = buf.split_at_mut(HEADER_LEN);
         let (data, rest): (&mut [u8], &mut [u8]) = rest.split_at_mut(self.data.length());
 
         header[0] = FRAME_START;
-        header[1] = self.data_type as u8;
-        header[2] = FRAME_B3;
-        header[3] = FRAME_B4;
+        header[1] = self.data_type.as_byte();
+        header[2] = self.header.b3;
+        header[3] = self.header.b4;
         header[4] = self.data.length() as u8;
 
         self.data.encode(data)?;
 
         if let Some(last) = rest.first_mut() {
-            *last = checksum(self.data_type, self.data_len, data);
-            Ok(5 + self.data_len + 1)
+            *last = frame_checksum::<C>(self.header, self.data_type, data);
+            Ok(length)
         } else {
             Err(EncodingError::BufferTooSmall)
         }
@@ -151,15 +523,40 @@ mod tests {
     fn checksum_test() {
         assert_eq!(
             0xa8,
-            checksum(DataType::ConnectRequest, 0x02, &[0xca, 0x01][0..2])
+            checksum(DataType::ConnectRequest, &[0xca, 0x01][0..2])
         );
     }
 
+    #[test]
+    fn verify_checksum_test() {
+        assert_eq!(Ok(()), verify_checksum(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]));
+        assert_eq!(
+            Err(DecodingError::InvalidChecksum {
+                data_type: DataType::ConnectResponse,
+                data_len: 1,
+                calculated: 0x54,
+                received: 0x55,
+            }),
+            verify_checksum(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x55])
+        );
+        assert_eq!(
+            Err(DecodingError::Incomplete { needed: Some(2) }),
+            verify_checksum(&[0xfc, 0x7a, 0x01, 0x30])
+        );
+    }
+
+    #[test]
+    fn fix_checksum_test() {
+        let mut frame = [0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x00];
+        assert_eq!(Ok(()), fix_checksum(&mut frame));
+        assert_eq!(0x54, frame[6]);
+        assert_eq!(Err(DecodingError::Incomplete { needed: Some(1) }), fix_checksum(&mut frame[..5]));
+    }
+
     #[test]
     fn parse_test() {
         let expected = Frame::new(
             DataType::GetInfoRequest,
-            0x10,
             &[0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
               0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00][0..16],
         );
@@ -175,12 +572,180 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_invalid_checksum_test() {
+        assert_eq!(
+            Err(DecodingError::InvalidChecksum {
+                data_type: DataType::ConnectRequest,
+                data_len: 2,
+                calculated: 0xa8,
+                received: 0xa9,
+            }),
+            Frame::parse(&[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa9])
+        );
+    }
+
+    #[test]
+    fn parse_bad_header_test() {
+        assert_eq!(Err(DecodingError::BadHeader), Frame::parse(&[0x00, 0xfc]));
+        assert_eq!(Err(DecodingError::BadHeader), Frame::parse(&[0xfc, 0x5a, 0x02, 0x30, 0x02]));
+    }
+
+    #[test]
+    fn parse_incomplete_test() {
+        assert_eq!(
+            Err(DecodingError::Incomplete { needed: Some(1) }),
+            Frame::parse(&[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01])
+        );
+        assert_eq!(
+            Err(DecodingError::Incomplete { needed: Some(2) }),
+            Frame::parse(&[0xfc, 0x5a, 0x01, 0x30, 0x02])
+        );
+    }
+
+    #[test]
+    fn scan_test() {
+        let connect = [0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8];
+
+        let (consumed, frame) = Frame::scan(&connect);
+        assert_eq!((8, DataType::ConnectRequest), (consumed, frame.unwrap().data_type));
+
+        // A bad checksum is skipped past, to the frame which follows
+        let mut buf = [0u8; 16];
+        buf[..8].copy_from_slice(&connect);
+        buf[7] = 0xa9;
+        buf[8..].copy_from_slice(&connect);
+        assert_eq!(16, Frame::scan(&buf).0);
+
+        assert_eq!(
+            (3, Err(DecodingError::Incomplete { needed: Some(2) })),
+            Frame::scan(&[0x01, 0x02, 0x03, 0xfc, 0x5a])
+        );
+        assert_eq!((2, Err(DecodingError::Incomplete { needed: None })), Frame::scan(&[0x01, 0x02]));
+    }
+
+    #[test]
+    fn parse_complete_test() {
+        let connect = [0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8];
+        assert_eq!(Frame::parse(&connect), Frame::parse_complete(&connect));
+        assert_eq!(Err(DecodingError::Truncated { needed: Some(1) }), Frame::parse_complete(&connect[..7]));
+        assert_eq!(Err(DecodingError::BadHeader), Frame::parse_complete(&[0x00]));
+    }
+
     #[test]
     fn encode_test() {
         let mut buf: [u8; 8] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-        let frame = Frame::new(DataType::ConnectRequest, 2, &[0xca, 0x01][0..2]);
+        let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][0..2]);
         let result = frame.encode(&mut buf);
         assert_eq!(Ok(8), result);
         assert_eq!([0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], buf);
     }
+
+    #[test]
+    fn encode_all_test() {
+        use super::super::encoding::encode_all;
+
+        let frames = [
+            Frame::new(DataType::ConnectRequest, &[0xca, 0x01][0..2]),
+            Frame::new(DataType::ConnectResponse, &[0x00][0..1]),
+        ];
+        let mut buf = [0u8; 15];
+        let mut ranges = [0..0, 0..0];
+
+        assert_eq!(Ok(15), encode_all(&frames, &mut buf, &mut ranges));
+        assert_eq!([0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], buf[ranges[0].clone()]);
+        assert_eq!([0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54], buf[ranges[1].clone()]);
+
+        assert_eq!(Err(EncodingError::BufferTooSmall), encode_all(&frames, &mut buf[..14], &mut ranges));
+        assert_eq!(Err(EncodingError::BufferTooSmall), encode_all(&frames, &mut buf, &mut ranges[..1]));
+    }
+
+    #[test]
+    fn encode_buffer_test() {
+        use super::super::encoding::EncodeBuffer;
+
+        let mut buf = [0u8; 10];
+        let mut tx = EncodeBuffer::new(&mut buf);
+        let connect = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][0..2]);
+
+        assert_eq!(Ok(0..8), tx.push(&connect));
+        assert_eq!(Err(EncodingError::BufferTooSmall), tx.push(&connect));
+        assert_eq!((8, 2), (tx.len(), tx.remaining()));
+
+        tx.clear();
+        assert!(tx.is_empty());
+    }
+
+    #[test]
+    fn byte_sink_test() {
+        use super::super::encoding::{ByteSink, EncodeBuffer};
+
+        let connect = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][0..2]);
+        let mut buf = [0u8; 12];
+        let mut sink = &mut buf[..];
+
+        assert_eq!(Ok(8), sink.push_encoded(&connect));
+        assert_eq!(4, sink.len());
+        assert_eq!(Err(EncodingError::BufferTooSmall), sink.push_encoded(&connect));
+        assert_eq!([0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], buf[..8]);
+
+        #[cfg(feature = "heapless")]
+        {
+            let mut vec = heapless::Vec::<u8, 8>::new();
+            assert_eq!(Ok(8), vec.push_encoded(&connect));
+            assert_eq!(Err(EncodingError::BufferTooSmall), ByteSink::push(&mut vec, 0x00));
+
+            let mut small = heapless::Vec::<u8, 4>::new();
+            assert_eq!(Err(EncodingError::BufferTooSmall), small.push_encoded(&connect));
+            assert!(small.is_empty());
+        }
+
+        let mut buf = [0u8; 12];
+        let mut tx = EncodeBuffer::new(&mut buf);
+        assert_eq!(Ok(8), ByteSink::push_encoded(&mut tx, &connect));
+        assert_eq!(Err(EncodingError::BufferTooSmall), ByteSink::push_encoded(&mut tx, &connect));
+        assert_eq!(8, tx.len());
+    }
+
+    #[test]
+    fn encode_uninit_test() {
+        use core::mem::MaybeUninit;
+        use super::super::encoding::encode_uninit;
+
+        let connect = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][0..2]);
+        let mut buf = [MaybeUninit::<u8>::uninit(); 7];
+        assert_eq!(Err(EncodingError::BufferTooSmall), encode_uninit(&connect, &mut buf));
+
+        // Room for it, but too long for a frame
+        let long = Frame::new(DataType::Unknown(0x99), &[0u8; MAX_DATA_LEN + 1][..]);
+        let mut buf = [MaybeUninit::<u8>::uninit(); HEADER_LEN + MAX_DATA_LEN + 2];
+        assert_eq!(Err(EncodingError::DataTooLong), encode_uninit(&long, &mut buf));
+    }
+
+    #[test]
+    fn header_test() {
+        let header = Header::new(0x02, 0x31);
+        let mut buf = [0u8; 8];
+        let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][0..2]).with_header(header);
+        assert_eq!(Ok(8), frame.encode(&mut buf));
+        assert_eq!([0xfc, 0x5a, 0x02, 0x31, 0x02, 0xca, 0x01, 0xa6], buf);
+
+        assert_eq!(Ok((EMPTY, frame)), Frame::parse_with_header(&buf, header));
+        assert_eq!(Err(DecodingError::BadHeader), Frame::parse(&buf));
+    }
+
+    #[test]
+    fn long_data_test() {
+        let data = [0x55u8; MAX_DATA_LEN + 1];
+        let mut buf = [0u8; MAX_DATA_LEN + 7];
+
+        let frame = Frame::new(DataType::Unknown(0x99), &data[..MAX_DATA_LEN]);
+        assert_eq!(Ok(MAX_DATA_LEN + 6), frame.encode(&mut buf));
+        assert_eq!(0xff, buf[4]);
+        assert_eq!(Ok((&buf[MAX_DATA_LEN + 6..], frame)), Frame::parse_with_limit(&buf, Header::CN105, MAX_DATA_LEN));
+        assert_eq!(Err(DecodingError::BadHeader), Frame::parse(&buf[..HEADER_LEN]));
+
+        let frame = Frame::new(DataType::Unknown(0x99), &data[..]);
+        assert_eq!(Err(EncodingError::DataTooLong), frame.encode(&mut buf));
+    }
 }
@@ -1,14 +1,22 @@
+use core::convert::TryFrom;
+use core::fmt;
+#[cfg(feature = "nom")]
 use nom::number::streaming::be_u8;
-use nom::{do_parse, IResult};
+#[cfg(all(feature = "nom", feature = "controller"))]
+use nom::number::streaming::be_u16;
+#[cfg(feature = "nom")]
+use nom::do_parse;
 
 use super::frame::{DataType, Frame};
-use super::types::{Power, Mode, Temperature, Fan, Vane, WideVane, ISee};
+use super::types::{Power, Mode, Temperature, TenthDegreesC, Setpoint, Fan, Vane, WideVane, ISee};
+use super::fault::FaultCode;
 
 use super::encoding::*;
+use super::decoding::{self, DecodingError, DecodingResult, ParseResult, UnknownValue};
 
 /// Decoded `Frame` data. Each variant contains a concrete type useful for
 /// representing the `Frame`'s `data_type`.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum FrameData {
     SetRequest(SetRequest),
     GetInfoRequest(GetInfoRequest),
@@ -21,10 +29,22 @@ pub enum FrameData {
     Unknown,
 }
 
+/// The most stack, in bytes, used by `FrameData::parse_untrusted` (and so by
+/// `Frame::parse_until`, `Frame::parse` and `FrameData::parse`) in a release
+/// build. Debug builds use several KB, mostly in nom's macros; build the
+/// parsers with optimisations on small parts, even in development. Check it
+/// against the sizes reported by building with `-Z emit-stack-sizes`.
+pub const PARSE_STACK_BOUND: usize = 512;
+
 impl FrameData {
-    /// Parses out the data from a given `Frame`
+    /// Parses out the data from a given `Frame`, returning any of the frame's
+    /// data left over after decoding along with it
+    ///
+    /// Data types whose decoding has been compiled out, by disabling the
+    /// `controller` or `device` feature, parse as `FrameData::Unknown`.
     ///
     /// ```
+    /// # #[cfg(feature = "controller")] {
     /// use mitsu_ac::protocol::{Frame, DataType, FrameData, ConnectResponse};
     ///
     /// let (_, frame) = Frame::parse(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]).unwrap();
@@ -36,28 +56,108 @@ impl FrameData {
     ///     FrameData::ConnectResponse(r) => println!("Received a connect response: {:?}", r),
     ///     _ => panic!("Unexpected frame"),
     /// }
+    /// # }
     /// ```
-    pub fn parse(frame: Frame<&[u8]>) -> IResult<&[u8], Self> {
-        match frame.data_type {
+    pub fn parse(frame: Frame<&[u8]>) -> DecodingResult<'_, Self> {
+        let result: ParseResult<'_, Self> = match frame.data_type {
+            #[cfg(feature = "device")]
             DataType::SetRequest => Self::parse_data_type(FrameData::SetRequest, frame.data),
+            #[cfg(feature = "device")]
             DataType::GetInfoRequest => Self::parse_data_type(FrameData::GetInfoRequest, frame.data),
+            #[cfg(feature = "device")]
             DataType::ConnectRequest => Self::parse_data_type(FrameData::ConnectRequest, frame.data),
+            #[cfg(not(feature = "device"))]
+            DataType::SetRequest | DataType::GetInfoRequest | DataType::ConnectRequest =>
+                Ok((&[], FrameData::Unknown)),
 
+            #[cfg(feature = "controller")]
             DataType::SetResponse => Self::parse_data_type(FrameData::SetResponse, frame.data),
+            #[cfg(feature = "controller")]
             DataType::GetInfoResponse => Self::parse_data_type(FrameData::GetInfoResponse, frame.data),
+            #[cfg(feature = "controller")]
             DataType::ConnectResponse => Self::parse_data_type(FrameData::ConnectResponse, frame.data),
+            #[cfg(not(feature = "controller"))]
+            DataType::SetResponse | DataType::GetInfoResponse | DataType::ConnectResponse =>
+                Ok((&[], FrameData::Unknown)),
+
+            DataType::Unknown(_) => Ok((&[], FrameData::Unknown)),
+        };
 
-            DataType::Unknown => Ok((&[], FrameData::Unknown)),
+        result.map_err(|e| match (frame.data_type, frame.data.first()) {
+            (DataType::GetInfoResponse, Some(&info_type)) if !matches!(e, decoding::ParseError::ImpossibleTemperature(_)) =>
+                DecodingError::MalformedInfo(InfoType::from_byte(info_type)),
+            _ => decoding::data_error(frame.data_type, e),
+        })
+    }
+
+    /// Parses out the data from a given `Frame` as `parse` does, but fails
+    /// with `TrailingData` if any of the frame's data is left over, rather
+    /// than returning it. Useful for spotting frames which aren't laid out
+    /// as expected. Data which decodes as `Unknown` is never reported, as
+    /// none of it is understood anyway.
+    ///
+    /// ```
+    /// # #[cfg(feature = "controller")] {
+    /// use mitsu_ac::protocol::{Frame, DataType, FrameData, DecodingError};
+    ///
+    /// let frame = Frame::new(DataType::ConnectResponse, &[0x00, 0x00][..]);
+    /// assert_eq!(
+    ///     Err(DecodingError::TrailingData { data_type: DataType::ConnectResponse, len: 1 }),
+    ///     FrameData::parse_exact(frame)
+    /// );
+    /// # }
+    /// ```
+    pub fn parse_exact(frame: Frame<&[u8]>) -> Result<Self, DecodingError> {
+        match Self::parse(frame)? {
+            (&[], data) |
+            (_, data @ FrameData::Unknown) |
+            (_, data @ FrameData::GetInfoResponse(GetInfoResponse::Unknown)) => Ok(data),
+            (rest, _) => Err(DecodingError::TrailingData { data_type: frame.data_type, len: rest.len() }),
         }
     }
 
-    fn parse_data_type<T: Parseable>(variant: fn (T) -> Self, data: &[u8]) -> IResult<&[u8], Self> {
-        let result: IResult<&[u8], T> = T::parse(data);
+    /// Finds the first frame in `data`, skipping any leading junk, and decodes
+    /// its data. On success, returns the input remaining after that frame.
+    ///
+    /// This never panics, whatever `data` contains, so it's safe to use
+    /// directly on buffers filled by an ISR or any other untrusted source.
+    ///
+    /// ```
+    /// # #[cfg(feature = "controller")] {
+    /// use mitsu_ac::protocol::{FrameData, ConnectResponse};
+    ///
+    /// let buf: &[u8] = &[0x42, 0x00, 0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54];
+    /// let (_, data) = FrameData::parse_untrusted(buf).unwrap();
+    ///
+    /// assert_eq!(data, FrameData::ConnectResponse(ConnectResponse::new(0)));
+    /// # }
+    /// ```
+    pub fn parse_untrusted(data: &[u8]) -> DecodingResult<'_, Self> {
+        let (data, _) = Frame::parse_until(data)?;
+        let (rest, frame) = Frame::parse(data)?;
+        let (_, data) = Self::parse(frame)?;
+        Ok((rest, data))
+    }
 
-        match result {
-            Ok((remaining_bytes, t)) => Ok((remaining_bytes, variant(t))),
-            Err(e) => Err(e),
-        }
+    /// Finds and decodes the first frame in `data`, as `parse_untrusted`
+    /// does, but treating `data` as all the input there is. Running out of
+    /// input, including finding no frame at all, is reported as `Truncated`
+    /// rather than `Incomplete`.
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::{FrameData, DecodingError};
+    ///
+    /// let log: &[u8] = &[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54, 0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00];
+    /// let (rest, _) = FrameData::parse_complete(log).unwrap();
+    ///
+    /// assert_eq!(Err(DecodingError::Truncated { needed: Some(1) }), FrameData::parse_complete(rest));
+    /// ```
+    pub fn parse_complete(data: &[u8]) -> DecodingResult<'_, Self> {
+        decoding::complete(Self::parse_untrusted(data))
+    }
+
+    fn parse_data_type<T: Parseable>(variant: fn (T) -> Self, data: &[u8]) -> ParseResult<'_, Self> {
+        T::parse(data).map(|(remaining_bytes, t)| (remaining_bytes, variant(t)))
     }
 
     fn data_type(&self) -> DataType {
@@ -70,14 +170,14 @@ impl FrameData {
             FrameData::GetInfoResponse(_) => DataType::GetInfoResponse,
             FrameData::ConnectResponse(_) => DataType::ConnectResponse,
 
-            _ => DataType::Unknown,
+            _ => DataType::Unknown(0xff),
         }
     }
 }
 
-impl Into<Frame<FrameData>> for FrameData {
-    fn into(self) -> Frame<FrameData> {
-        Frame::new(self.data_type(), self.length(), self)
+impl From<FrameData> for Frame<FrameData> {
+    fn from(data: FrameData) -> Self {
+        Frame::new(data.data_type(), data)
     }
 }
 
@@ -88,10 +188,9 @@ impl SizedEncoding for FrameData {
             FrameData::GetInfoRequest(data) => data.length(),
             FrameData::ConnectRequest(data) => data.length(),
 
-            FrameData::SetResponse(_)
-            | FrameData::GetInfoResponse(_)
-            | FrameData::ConnectResponse(_) =>
-                0,
+            FrameData::SetResponse(data) => data.length(),
+            FrameData::GetInfoResponse(data) => data.length(),
+            FrameData::ConnectResponse(data) => data.length(),
 
             FrameData::Unknown => 0,
         }
@@ -99,12 +198,28 @@ impl SizedEncoding for FrameData {
 }
 
 impl Encodable for FrameData {
+    #[cfg_attr(not(all(feature = "controller", feature = "device")), allow(unused_variables))]
     fn encode(&self, buffer: &mut [u8]) -> Result<usize, EncodingError> {
         match self {
+            #[cfg(feature = "controller")]
             FrameData::SetRequest(data) => data.encode(buffer),
+            #[cfg(feature = "controller")]
             FrameData::GetInfoRequest(data) => data.encode(buffer),
+            #[cfg(feature = "controller")]
             FrameData::ConnectRequest(data) => data.encode(buffer),
+            #[cfg(not(feature = "controller"))]
+            FrameData::SetRequest(_)
+            | FrameData::GetInfoRequest(_)
+            | FrameData::ConnectRequest(_) =>
+                Err(EncodingError::NotImplemented),
 
+            #[cfg(feature = "device")]
+            FrameData::SetResponse(data) => data.encode(buffer),
+            #[cfg(feature = "device")]
+            FrameData::GetInfoResponse(data) => data.encode(buffer),
+            #[cfg(feature = "device")]
+            FrameData::ConnectResponse(data) => data.encode(buffer),
+            #[cfg(not(feature = "device"))]
             FrameData::SetResponse(_)
             | FrameData::GetInfoResponse(_)
             | FrameData::ConnectResponse(_) =>
@@ -115,8 +230,8 @@ impl Encodable for FrameData {
     }
 }
 
-trait Parseable : Sized {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self>;
+pub(super) trait Parseable : Sized {
+    fn parse(data: &[u8]) -> ParseResult<'_, Self>;
 }
 
 /// Sets one or more of the device's settings:
@@ -151,49 +266,120 @@ trait Parseable : Sized {
 /// |   13 | Wide Vane |
 /// |   14 | Temperature (as half-degrees c + offset) |
 /// |   15 | Unused |
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub struct SetRequest {
     pub power: Option<Power>,
     pub mode: Option<Mode>,
-    pub temp: Option<Temperature>,
+    pub temp: Option<Setpoint>,
     pub fan: Option<Fan>,
     pub vane: Option<Vane>,
     pub widevane: Option<WideVane>,
 }
 
+/// Requests changing a single setting
+///
+/// ```
+/// use mitsu_ac::protocol::SetRequest;
+/// use mitsu_ac::protocol::types::{Power, Setpoint, TenthDegreesC};
+///
+/// assert_eq!(Some(Power::On), SetRequest::power_on().power);
+///
+/// let request = SetRequest::set_temperature(Setpoint::new(TenthDegreesC(215)).unwrap());
+/// assert_eq!(None, request.power);
+/// ```
+impl SetRequest {
+    pub fn power_on() -> Self {
+        SetRequest { power: Some(Power::On), ..SetRequest::default() }
+    }
+
+    pub fn power_off() -> Self {
+        SetRequest { power: Some(Power::Off), ..SetRequest::default() }
+    }
+
+    pub fn set_temperature(setpoint: Setpoint) -> Self {
+        SetRequest { temp: Some(setpoint), ..SetRequest::default() }
+    }
+
+    pub fn set_mode(mode: Mode) -> Self {
+        SetRequest { mode: Some(mode), ..SetRequest::default() }
+    }
+}
+
+/// A request writing every setting in `settings`, e.g. to restore a saved
+/// profile. i-see can't be set, so it's left out.
+impl From<&Settings> for SetRequest {
+    fn from(settings: &Settings) -> Self {
+        SetRequest {
+            power: Some(settings.power),
+            mode: Some(settings.mode),
+            temp: Some(settings.setpoint),
+            fan: Some(settings.fan),
+            vane: Some(settings.vane),
+            widevane: Some(settings.widevane),
+        }
+    }
+}
+
+#[cfg(all(feature = "nom", feature = "device"))]
 impl Parseable for SetRequest {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(data,
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        let (rest, (flags, widevane_flag, fields)) = decoding::from_nom(do_parse!(data,
             tag!(&[0x01]) >>
-            flags: bits!(do_parse!(
-                take_bits!(u8, 3) >>
-                vane: take_bits!(u8, 1) >>
-                fan: take_bits!(u8, 1) >>
-                temp: take_bits!(u8, 1) >>
-                mode: take_bits!(u8, 1) >>
-                power: take_bits!(u8, 1) >>
-                take_bits!(u8, 7) >>
-                widevane: take_bits!(u8, 1) >>
-                ((power, mode, temp, fan, vane, widevane))
-            )) >>
-            power: cond!(flags.0 == 1, map_opt!(be_u8, Power::from_repr)) >>
-            mode: cond!(flags.1 == 1, map_opt!(be_u8, Mode::from_repr)) >>
-            _temp_mapped: cond!(flags.2 == 1, map!(be_u8, |b| Temperature::SetpointMapped { value: b }))>>
-            fan: cond!(flags.3 == 1, map_opt!(be_u8, Fan::from_repr)) >>
-            vane: cond!(flags.4 == 1, map_opt!(be_u8, Vane::from_repr)) >>
+            flags: be_u8 >>
+            widevane_flag: be_u8 >>
+            power: be_u8 >>
+            mode: be_u8 >>
+            temp_mapped: be_u8 >>
+            fan: be_u8 >>
+            vane: be_u8 >>
             take!(5) >>
-            widevane: cond!(flags.5 == 1, map_opt!(be_u8, WideVane::from_repr)) >>
-            temp: cond!(flags.2 == 1, map!(be_u8, |b| Temperature::HalfDegreesCPlusOffset { value: b })) >>
+            widevane: be_u8 >>
+            temp_half_deg: be_u8 >>
             take!(1) >>
-            (SetRequest {
-                power,
-                mode,
-                temp,
-                fan,
-                vane,
-                widevane,
-            })
-        )
+            ((flags, widevane_flag, SetRequestFields { power, mode, temp_mapped, fan, vane, widevane, temp_half_deg }))
+        ))?;
+        Ok((rest, SetRequest::from_fields(flags, widevane_flag, fields)?))
+    }
+}
+
+/// The value bytes of a `SetRequest`, read whether or not they're flagged
+#[cfg(feature = "device")]
+pub(super) struct SetRequestFields {
+    pub power: u8,
+    pub mode: u8,
+    pub temp_mapped: u8,
+    pub fan: u8,
+    pub vane: u8,
+    pub widevane: u8,
+    pub temp_half_deg: u8,
+}
+
+#[cfg(feature = "device")]
+impl SetRequest {
+    // Every field has a fixed position, so a flagged field can be checked
+    // for a value: a setpoint needs one of its temperature bytes, and no
+    // mode or wide vane setting is zero.
+    pub(super) fn from_fields(flags: u8, widevane_flag: u8, fields: SetRequestFields) -> Result<Self, decoding::ParseError> {
+        let flagged = |bit: u8| flags & bit != 0;
+
+        let temp = match (flagged(0b00000100), fields.temp_half_deg, fields.temp_mapped) {
+            (false, _, _) => None,
+            (true, 0, 0) => return Err(decoding::ParseError::Inconsistent),
+            (true, 0, value) => Some(Temperature::SetpointMapped { value }.checked()?),
+            (true, value, _) => Some(Temperature::HalfDegreesCPlusOffset { value }.checked()?),
+        };
+        if (flagged(0b00000010) && fields.mode == 0) || (widevane_flag & 0b00000001 != 0 && fields.widevane == 0) {
+            return Err(decoding::ParseError::Inconsistent);
+        }
+
+        Ok(SetRequest {
+            power: Some(Power::from_byte(fields.power)).filter(|_| flagged(0b00000001)),
+            mode: Some(Mode::from_byte(fields.mode)).filter(|_| flagged(0b00000010)),
+            temp: temp.map(|t| Setpoint(t.celsius_tenths())),
+            fan: Some(Fan::from_byte(fields.fan)).filter(|_| flagged(0b00001000)),
+            vane: Some(Vane::from_byte(fields.vane)).filter(|_| flagged(0b00010000)),
+            widevane: Some(WideVane::from_byte(fields.widevane)).filter(|_| widevane_flag & 0b00000001 != 0),
+        })
     }
 }
 
@@ -201,8 +387,9 @@ impl FixedSizeEncoding for SetRequest {
     const LENGTH: usize = 0x10;
 }
 
+#[cfg(feature = "controller")]
 impl Encodable for SetRequest {
-    fn encode<'a>(&self, buf: &'a mut [u8]) -> Result<usize, EncodingError> {
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
         if buf.len() != Self::LENGTH {
             Err(EncodingError::BufferTooSmall)
         } else {
@@ -222,24 +409,25 @@ impl Encodable for SetRequest {
     }
 }
 
+#[cfg(feature = "controller")]
 impl SetRequest {
     fn encode_flags<'a>(&self, into: &'a mut [u8]) -> Result<&'a [u8], EncodingError> {
         if into.len() != 2 { return Err(EncodingError::BufferTooSmall); }
 
-        into[0] = 0x00u8 |
+        into[0] =
             (match self.power { Some(_) => 0b00000001, _ => 0 }) |
             (match self.mode  { Some(_) => 0b00000010, _ => 0 }) |
             (match self.temp  { Some(_) => 0b00000100, _ => 0 }) |
             (match self.fan   { Some(_) => 0b00001000, _ => 0 }) |
             (match self.vane  { Some(_) => 0b00010000, _ => 0 });
-        into[1] = 0x00u8 |
-            (match self.widevane { Some(_) => 0b00000001, _ => 0 });
+        into[1] =
+            match self.widevane { Some(_) => 0b00000001, _ => 0 };
         Ok(into)
     }
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum InfoType {
     Settings     = 0x02,
     RoomTemp     = 0x03,
@@ -250,41 +438,106 @@ pub enum InfoType {
     Unknown      = 0xff,
 }
 
-impl From<u8> for InfoType {
-    fn from(byte: u8) -> Self {
+impl InfoType {
+    /// Every known `InfoType`
+    pub const ALL: [InfoType; 6] = [
+        InfoType::Settings,
+        InfoType::RoomTemp,
+        InfoType::Type4,
+        InfoType::Timers,
+        InfoType::Status,
+        InfoType::MaybeStandby,
+    ];
+
+    /// The `InfoType`s worth polling to keep track of the unit: those we
+    /// decode into something other than `GetInfoResponse::Unknown`.
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::{Frame, FrameData, GetInfoRequest, InfoType};
+    ///
+    /// for &info_type in InfoType::POLLED.iter() {
+    ///     let frame: Frame<FrameData> = FrameData::GetInfoRequest(GetInfoRequest::new(info_type)).into();
+    ///     // ... send `frame` and wait for the response
+    /// }
+    /// ```
+    pub const POLLED: [InfoType; 4] = [
+        InfoType::Settings,
+        InfoType::RoomTemp,
+        InfoType::Type4,
+        InfoType::Status,
+    ];
+
+    /// Decodes a byte, treating any type we don't know about as `Unknown`
+    pub fn from_byte(byte: u8) -> Self {
+        Self::try_from(byte).unwrap_or(InfoType::Unknown)
+    }
+}
+
+impl TryFrom<u8> for InfoType {
+    type Error = UnknownValue;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
         match byte {
-            0x02 => InfoType::Settings,
-            0x03 => InfoType::RoomTemp,
-            0x04 => InfoType::Type4,
-            0x05 => InfoType::Timers,
-            0x06 => InfoType::Status,
-            0x09 => InfoType::MaybeStandby,
-            _ => InfoType::Unknown,
+            0x02 => Ok(InfoType::Settings),
+            0x03 => Ok(InfoType::RoomTemp),
+            0x04 => Ok(InfoType::Type4),
+            0x05 => Ok(InfoType::Timers),
+            0x06 => Ok(InfoType::Status),
+            0x09 => Ok(InfoType::MaybeStandby),
+            _ => Err(UnknownValue { type_name: "InfoType", byte }),
         }
     }
 }
 
 /// Requests the given InfoType data from the device
-#[derive(Debug, Eq, PartialEq)]
+///
+/// Encoded as the info type followed by 15 bytes of zero padding. When
+/// parsing, anything from just the info type up to the full 16 bytes is
+/// accepted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct GetInfoRequest(InfoType);
 
 
 impl GetInfoRequest {
+    pub const SETTINGS: Self = Self(InfoType::Settings);
+    pub const ROOM_TEMP: Self = Self(InfoType::RoomTemp);
+    pub const TYPE4: Self = Self(InfoType::Type4);
+    pub const TIMERS: Self = Self(InfoType::Timers);
+    pub const STATUS: Self = Self(InfoType::Status);
+    pub const MAYBE_STANDBY: Self = Self(InfoType::MaybeStandby);
+
     pub fn new(info_type: InfoType) -> Self {
         Self(info_type)
     }
+
+    /// The type of information requested
+    pub fn info_type(&self) -> InfoType {
+        self.0
+    }
 }
 
+#[cfg(feature = "device")]
+impl GetInfoRequest {
+    // Only the info type byte means anything. Some adapters send it with less
+    // than the usual 15 bytes of zero padding, so whatever padding is present
+    // is accepted.
+    pub(super) fn padding(data: &[u8]) -> usize {
+        data.len().clamp(1, Self::LENGTH) - 1
+    }
+}
+
+#[cfg(all(feature = "nom", feature = "device"))]
 impl Parseable for GetInfoRequest {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(data,
-            info_type: map!(be_u8, InfoType::from) >>
-            take!(15) >>
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(data,
+            info_type: map!(be_u8, InfoType::from_byte) >>
+            take!(GetInfoRequest::padding(data)) >>
             (GetInfoRequest(info_type))
-        )
+        ))
     }
 }
 
+#[cfg(feature = "controller")]
 impl Encodable for GetInfoRequest {
     fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
         if buf.len() != Self::LENGTH {
@@ -302,25 +555,27 @@ impl FixedSizeEncoding for GetInfoRequest {
 }
 
 /// The preamble that tells the device we're connected and want to talk
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ConnectRequest;
 
 impl ConnectRequest {
     // We have no idea what these magic values mean or if we can use anything
     // else, but they seem to do the trick...
-    const BYTE1: u8 = 0xca;
-    const BYTE2: u8 = 0x01;
+    pub(super) const BYTE1: u8 = 0xca;
+    pub(super) const BYTE2: u8 = 0x01;
 }
 
+#[cfg(all(feature = "nom", feature = "device"))]
 impl Parseable for ConnectRequest {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(data,
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(data,
             tag!(&[Self::BYTE1, Self::BYTE2]) >>
             (ConnectRequest)
-        )
+        ))
     }
 }
 
+#[cfg(feature = "controller")]
 impl Encodable for ConnectRequest {
     fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
         if buf.len() != Self::LENGTH {
@@ -340,71 +595,200 @@ impl FixedSizeEncoding for ConnectRequest {
 /// Response to the SetRequest
 ///
 /// The data is opaque, and not yet understood.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct SetResponse;
 
+impl FixedSizeEncoding for SetResponse {
+    const LENGTH: usize = 0x10;
+}
+
+/// Encoded as all zeroes, which units seem to accept
+#[cfg(feature = "device")]
+impl Encodable for SetResponse {
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
+        if buf.len() != Self::LENGTH { return Err(EncodingError::BufferTooSmall); }
+        for i in buf.iter_mut() { *i = 0 }
+        Ok(Self::LENGTH)
+    }
+}
+
+#[cfg(all(feature = "nom", feature = "controller"))]
 impl Parseable for SetResponse {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(data,
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(data,
             take!(16) >>
             (SetResponse)
-        )
+        ))
+    }
+}
+
+/// The unit's settings, as reported in a `Settings` info response
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Settings {
+    pub power: Power,
+    pub mode: Mode,
+    pub setpoint: Setpoint,
+    pub fan: Fan,
+    pub vane: Vane,
+    pub widevane: WideVane,
+    pub isee: ISee,
+}
+
+impl Settings {
+    /// A reasonable guess at the unit's settings before it has reported them:
+    /// off, in auto mode with a 22°C setpoint, and with the fan and vanes
+    /// left to the unit.
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::Settings;
+    /// use mitsu_ac::protocol::types::{Power, TenthDegreesC};
+    ///
+    /// let settings = Settings::power_on_defaults();
+    /// assert_eq!(settings.power, Power::Off);
+    /// assert_eq!(settings.setpoint.celsius_tenths(), TenthDegreesC(220));
+    /// ```
+    pub fn power_on_defaults() -> Self {
+        Settings {
+            power: Power::Off,
+            mode: Mode::Auto,
+            setpoint: Setpoint(TenthDegreesC(220)),
+            fan: Fan::Auto,
+            vane: Vane::Auto,
+            widevane: WideVane::Center,
+            isee: ISee::Off,
+        }
+    }
+
+    /// Writes a compact one-line summary, such as
+    /// `ON heat 21.5°C fan:auto vane:swing`, for a character LCD or debug
+    /// console. Any `fmt::Write` will do, including `heapless::String`.
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::Settings;
+    ///
+    /// let mut line = String::new();
+    /// Settings::power_on_defaults().write_summary(&mut line).unwrap();
+    /// assert_eq!("OFF auto 22.0°C fan:auto vane:auto", line);
+    /// ```
+    pub fn write_summary<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        let tenths = self.setpoint.celsius_tenths().0;
+        let sign = if tenths < 0 { "-" } else { "" };
+        let tenths = tenths.unsigned_abs();
+
+        write!(out, "{} ", self.power.as_str())?;
+        write_lowercase(out, self.mode.as_str())?;
+        write!(out, " {}{}.{}°C fan:", sign, tenths / 10, tenths % 10)?;
+        write_lowercase(out, self.fan.as_str())?;
+        out.write_str(" vane:")?;
+        write_lowercase(out, self.vane.as_str())
+    }
+
+    // The mode byte of a settings response has 0x08 added when i-see is on,
+    // as in SwiCago/HeatPump. Modes go up to 0x08 (auto), so this isn't a
+    // single flag bit.
+    #[cfg(feature = "controller")]
+    pub(super) fn decode_mode_and_isee(byte: u8) -> (Mode, ISee) {
+        if byte > 0x08 {
+            (Mode::from_byte(byte - 0x08), ISee::On)
+        } else {
+            (Mode::from_byte(byte), ISee::Off)
+        }
+    }
+
+    #[cfg(feature = "device")]
+    fn encode_mode_and_isee(&self) -> u8 {
+        let mode = self.mode.encoded_as_byte();
+        match self.isee {
+            ISee::On => mode.wrapping_add(0x08),
+            ISee::Off => mode,
+        }
     }
 }
 
+fn write_lowercase<W: fmt::Write>(out: &mut W, s: &str) -> fmt::Result {
+    s.chars().try_for_each(|c| out.write_char(c.to_ascii_lowercase()))
+}
+
 /// Response to a GetInfoRequest
 ///
 /// Includes the information requested in the original request. We don't
 /// currently parse all of the known `InfoType` responses, and there are also
 /// unknown `InfoType`s. For those, we return a `GetInfoResponse::Unknown`.
-#[derive(Debug, PartialEq, Eq)]
+/// A response for a supported `InfoType` which can't be decoded is an error,
+/// `DecodingError::MalformedInfo`, or `DecodingError::ImpossibleTemperature`
+/// when it holds a temperature no unit would send.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum GetInfoResponse {
-    Settings {
-        power: Power,
-        mode: Mode,
-        setpoint: Temperature,
-        fan: Fan,
-        vane: Vane,
-        widevane: WideVane,
-        isee: ISee,
-    },
+    Settings(Settings),
     RoomTemperature { temperature: Temperature },
+    /// The unit's fault state, from the `Type4` info type. `error_code` is
+    /// `0x8000` when there is no fault. The layout of this response is based
+    /// on community captures and may not hold for every unit.
+    Faults { error_code: u16, fault: Option<FaultCode> },
     Status { compressor_frequency: u8, operating: u8 },
     Unknown,
 }
 
+impl GetInfoResponse {
+    /// The offset in `RoomTemp` data of the suspected humidity byte
+    const HUMIDITY_OFFSET: usize = 7;
+
+    /// **Experimental.** The relative humidity, in percent, from the data of
+    /// a `RoomTemp` response. Some ducted units are suspected of reporting
+    /// it in the byte after the room temperature, which is otherwise unused.
+    /// This is unconfirmed, so it's not decoded as part of
+    /// `RoomTemperature`, and may be removed or moved to another byte.
+    ///
+    /// `None` for other responses, and where the byte is zero or more than
+    /// 100, as on units which don't report humidity.
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::GetInfoResponse;
+    ///
+    /// let data = [0x03, 0x00, 0x00, 0x0b, 0x00, 0x00, 0xab, 0x2d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    /// assert_eq!(Some(45), GetInfoResponse::experimental_humidity(&data));
+    /// ```
+    pub fn experimental_humidity(data: &[u8]) -> Option<u8> {
+        if data.first() != Some(&(InfoType::RoomTemp as u8)) {
+            return None;
+        }
+        data.get(Self::HUMIDITY_OFFSET).copied().filter(|&humidity| (1..=100).contains(&humidity))
+    }
+}
+
 
+#[cfg(all(feature = "nom", feature = "controller"))]
 impl GetInfoResponse {
-    fn decode_settings(input: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(input,
+    fn decode_settings(input: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(input,
             tag!(&[InfoType::Settings as u8]) >>
             take!(2) >>
-            power: map_opt!(be_u8, Power::from_repr) >>
-            mode_and_isee: bits!(tuple!(
-                take_bits!(u8, 4),
-                map_opt!(take_bits!(u8, 1), ISee::from_repr),
-                map_opt!(take_bits!(u8, 3), Mode::from_repr))) >>
+            power: map!(be_u8, Power::from_byte) >>
+            mode_and_isee: map!(be_u8, Settings::decode_mode_and_isee) >>
+            mode: value!(mode_and_isee.0) >>
             isee: value!(mode_and_isee.1) >>
-            mode: value!(mode_and_isee.2) >>
             setpoint_mapped: map!(be_u8, |b| Temperature::SetpointMapped { value: b })>>
-            fan: map_opt!(be_u8, Fan::from_repr) >>
-            vane: map_opt!(be_u8, Vane::from_repr) >>
+            fan: map!(be_u8, Fan::from_byte) >>
+            vane: map!(be_u8, Vane::from_byte) >>
             take!(2) >>
-            widevane: map_opt!(be_u8, WideVane::from_repr) >>
+            widevane: map!(be_u8, WideVane::from_byte) >>
             setpoint_half_deg: map!(be_u8, |b| Temperature::HalfDegreesCPlusOffset { value: b }) >>
             setpoint: value!(match (setpoint_mapped, setpoint_half_deg) {
                 (s, Temperature::HalfDegreesCPlusOffset { value: 0 }) => s,
                 (_, s) => s,
             }) >>
             take!(4) >>
-            (GetInfoResponse::Settings {
+            ((power, mode, fan, vane, widevane, setpoint, isee))
+        )).and_then(|(rest, (power, mode, fan, vane, widevane, setpoint, isee))| {
+            let setpoint = Setpoint(setpoint.checked()?.celsius_tenths());
+            Ok((rest, GetInfoResponse::Settings(Settings {
                 power, mode, fan, vane, widevane, setpoint, isee
-            })
-        )
+            })))
+        })
     }
 
-    fn decode_room_temp(input: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(input,
+    fn decode_room_temp(input: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(input,
             tag!(&[InfoType::RoomTemp as u8]) >>
             take!(2) >>
             mapped: map!(be_u8, |b| Temperature::RoomTempMapped { value: b }) >>
@@ -415,71 +799,230 @@ impl GetInfoResponse {
                 (Temperature::HalfDegreesCPlusOffset { value: 0 }, t) => t,
                 (t, _) => t,
             }) >>
-            (GetInfoResponse::RoomTemperature { temperature })
-        )
+            (temperature)
+        )).and_then(|(rest, temperature)| {
+            Ok((rest, GetInfoResponse::RoomTemperature { temperature: temperature.checked()? }))
+        })
     }
 
-    fn decode_timer(input: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(input,
+    fn decode_faults(input: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(input,
+            tag!(&[InfoType::Type4 as u8]) >>
+            take!(3) >>
+            error_code: be_u16 >>
+            fault: map!(be_u8, FaultCode::from_short_code) >>
+            take!(9) >>
+            (GetInfoResponse::Faults { error_code, fault })
+        ))
+    }
+
+    fn decode_timer(input: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(input,
             tag!(&[InfoType::Timers as u8]) >>
             (GetInfoResponse::Unknown)
-        )
+        ))
     }
 
-    fn decode_status(input: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(input,
+    fn decode_status(input: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(input,
             tag!(&[InfoType::Status as u8]) >>
             take!(2) >>
             compressor_frequency: be_u8 >>
             operating: be_u8 >>
+            take!(11) >>
             (GetInfoResponse::Status { compressor_frequency, operating })
-        )
+        ))
     }
+}
+
+impl FixedSizeEncoding for GetInfoResponse {
+    const LENGTH: usize = 0x10;
+}
 
-    fn decode_unknown(input: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(input, (GetInfoResponse::Unknown))
+/// Encoded in the same layout it's decoded from, with every byte that isn't
+/// understood set to zero. `Unknown` can't be encoded.
+#[cfg(feature = "device")]
+impl Encodable for GetInfoResponse {
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
+        if buf.len() != Self::LENGTH { return Err(EncodingError::BufferTooSmall); }
+        for i in buf.iter_mut() { *i = 0 }
+
+        match self {
+            GetInfoResponse::Settings(settings) => {
+                let setpoint = settings.setpoint.celsius_tenths();
+                buf[0] = InfoType::Settings as u8;
+                buf[3] = settings.power.encoded_as_byte();
+                buf[4] = settings.encode_mode_and_isee();
+                buf[5] = setpoint.encode_as_setpoint_mapped();
+                buf[6] = settings.fan.encoded_as_byte();
+                buf[7] = settings.vane.encoded_as_byte();
+                buf[10] = settings.widevane.encoded_as_byte();
+                buf[11] = setpoint.encode_as_half_deg_plus_offset();
+            },
+            GetInfoResponse::RoomTemperature { temperature } => {
+                let temperature = temperature.celsius_tenths();
+                buf[0] = InfoType::RoomTemp as u8;
+                buf[3] = temperature.encode_as_room_temp_mapped();
+                buf[6] = temperature.encode_as_half_deg_plus_offset();
+            },
+            GetInfoResponse::Faults { error_code, fault } => {
+                buf[0] = InfoType::Type4 as u8;
+                buf[4..6].copy_from_slice(&error_code.to_be_bytes());
+                buf[6] = fault.and_then(|f| f.short_code()).unwrap_or(0);
+            },
+            GetInfoResponse::Status { compressor_frequency, operating } => {
+                buf[0] = InfoType::Status as u8;
+                buf[3] = *compressor_frequency;
+                buf[4] = *operating;
+            },
+            GetInfoResponse::Unknown => return Err(EncodingError::NotImplemented),
+        }
+
+        Ok(Self::LENGTH)
     }
 }
 
+#[cfg(feature = "controller")]
 impl Parseable for GetInfoResponse {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self> {
-        alt!(data,
-             Self::decode_settings |
-             Self::decode_room_temp |
-             Self::decode_timer |
-             Self::decode_status |
-             Self::decode_unknown
-        )
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        match data.first().map(|b| InfoType::from_byte(*b)) {
+            Some(InfoType::Settings) => Self::decode_settings(data),
+            Some(InfoType::RoomTemp) => Self::decode_room_temp(data),
+            Some(InfoType::Type4) => Self::decode_faults(data),
+            Some(InfoType::Timers) => Self::decode_timer(data),
+            Some(InfoType::Status) => Self::decode_status(data),
+            Some(_) => Ok((data, GetInfoResponse::Unknown)),
+            None => Err(decoding::ParseError::Incomplete(Some(1))),
+        }
     }
 }
 
 /// Response to our `ConnectRequest`
 ///
 /// Once we see this response, we know the device is ready to talk.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ConnectResponse(u8);
 
 impl ConnectResponse {
     pub fn new(b: u8) -> Self { ConnectResponse(b) }
 }
 
+impl FixedSizeEncoding for ConnectResponse {
+    const LENGTH: usize = 1;
+}
+
+#[cfg(feature = "device")]
+impl Encodable for ConnectResponse {
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodingError> {
+        if buf.len() != Self::LENGTH { return Err(EncodingError::BufferTooSmall); }
+        buf[0] = self.0;
+        Ok(Self::LENGTH)
+    }
+}
+
+#[cfg(all(feature = "nom", feature = "controller"))]
 impl Parseable for ConnectResponse {
-    fn parse(data: &[u8]) -> IResult<&[u8], Self> {
-        do_parse!(data,
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        decoding::from_nom(do_parse!(data,
             b: be_u8 >>
             (Self(b))
-        )
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
+
     use super::*;
     use super::super::types::TenthDegreesC;
+    use crate::rng::XorShift64;
 
     const EMPTY: &[u8] = &[];
 
+    // Painting the stack and reading back below the current frame reads
+    // memory no longer in use, which is undefined behaviour, and what it
+    // finds depends on the optimiser. Only run by hand, with `--ignored`, as
+    // a rough check; `-Z emit-stack-sizes` on nightly gives real figures.
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    #[ignore = "reads dead stack memory, which is undefined behaviour"]
+    fn parse_stack_bound_test() {
+        const PAINTED: usize = 16 * 1024;
+        const PAINT: u8 = 0xa5;
+
+        #[inline(never)]
+        fn paint() -> usize {
+            let mut stack = [0u8; PAINTED];
+            for b in stack.iter_mut() {
+                unsafe { core::ptr::write_volatile(b, PAINT) }
+            }
+            core::hint::black_box(stack.as_ptr()) as usize
+        }
+
+        #[inline(never)]
+        fn stack_pointer() -> usize {
+            let marker = 0u8;
+            core::hint::black_box(&marker) as *const u8 as usize
+        }
+
+        // Debug builds aren't covered by the bound, only checked for sanity
+        let bound = if cfg!(debug_assertions) { 8 * 1024 } else { PARSE_STACK_BOUND };
+
+        let frames: [&[u8]; 3] = [
+            &[0xfc, 0x62, 0x01, 0x30, 0x10, 0x02, 0x00, 0x00, 0x01, 0x01, 0x0f, 0x00,
+              0x07, 0x00, 0x00, 0x03, 0x94, 0x00, 0x00, 0x00, 0x00, 0x00],
+            &[0xfc, 0x41, 0x01, 0x30, 0x10, 0x01, 0x1f, 0x01, 0x01, 0x01, 0x0f, 0x00,
+              0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x94, 0x00, 0x00],
+            &[0x00, 0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54],
+        ];
+
+        for frame in frames.iter() {
+            let top = stack_pointer();
+            let bottom = paint();
+            let _ = core::hint::black_box(FrameData::parse_untrusted(core::hint::black_box(frame)));
+
+            let mut lowest_used = bottom;
+            while lowest_used < top
+                && unsafe { core::ptr::read_volatile(lowest_used as *const u8) } == PAINT {
+                lowest_used += 1;
+            }
+            assert!(top - lowest_used <= bound, "used {} bytes", top - lowest_used);
+        }
+    }
+
     #[test]
+    fn parse_untrusted_never_panics_test() {
+        let mut rng = XorShift64::new(0x1234_5678);
+        let mut next = || rng.next_u64() as u8;
+
+        let data_types = [0x41, 0x42, 0x5a, 0x61, 0x62, 0x7a, 0x00];
+        let mut buf = [0u8; 40];
+        for _ in 0..20_000 {
+            let len = next() as usize % buf.len();
+            for b in buf.iter_mut() { *b = next(); }
+
+            // Bias towards well-formed headers so payload decoders get exercised
+            if next() % 4 != 0 {
+                let payload_len = (len.saturating_sub(6) as u8).min(next() % 0x12);
+                buf[0] = 0xfc;
+                buf[1] = data_types[next() as usize % data_types.len()];
+                buf[2] = 0x01;
+                buf[3] = 0x30;
+                buf[4] = payload_len;
+                let data_type = DataType::from_byte(buf[1]);
+                let end = 5 + payload_len as usize;
+                if end < buf.len() {
+                    buf[end] = super::super::checksum(data_type, &buf[5..end]);
+                }
+            }
+
+            let _ = FrameData::parse_untrusted(&buf[..len]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "device")]
     fn parse_get_info_request_test() {
         let data: &[u8] = &[
             0x02, 0x00, 0x00, 0x00,
@@ -489,9 +1032,19 @@ mod tests {
         ];
         let result = FrameData::parse_data_type(FrameData::GetInfoRequest, data);
         assert_eq!(Ok((EMPTY, FrameData::GetInfoRequest(GetInfoRequest(InfoType::Settings)))), result);
+
+        let result = FrameData::parse_data_type(FrameData::GetInfoRequest, &data[..1]);
+        assert_eq!(Ok((EMPTY, FrameData::GetInfoRequest(GetInfoRequest(InfoType::Settings)))), result);
+
+        let frame = Frame::new(DataType::GetInfoRequest, &[0x03, 0x00, 0x00][..]);
+        assert_eq!(Ok(FrameData::GetInfoRequest(GetInfoRequest(InfoType::RoomTemp))), FrameData::parse_exact(frame));
+
+        let frame = Frame::new(DataType::GetInfoRequest, EMPTY);
+        assert_eq!(Err(DecodingError::ShortData { needed: Some(1) }), FrameData::parse(frame));
     }
 
     #[test]
+    #[cfg(feature = "controller")]
     fn encode_get_info_request_test() {
         let mut buf: [u8; 16] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
         let expected: [u8; 16] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
@@ -501,6 +1054,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "device")]
     fn parse_set_request_test() {
         let data: &[u8] = &[
             0x01, 0x1f, 0x1,
@@ -517,11 +1071,87 @@ mod tests {
             fan: Some(Fan::Auto),
             vane: Some(Vane::Swing),
             widevane: Some(WideVane::LL),
-            temp: Some(Temperature::HalfDegreesCPlusOffset { value: TenthDegreesC(210).encode_as_half_deg_plus_offset() }),
+            temp: Some(Setpoint(TenthDegreesC(210))),
         }))), result);
     }
 
     #[test]
+    #[cfg(feature = "device")]
+    fn parse_inconsistent_set_request_test() {
+        // The setpoint is flagged, but neither temperature byte is set
+        let mut data = [0u8; 16];
+        data[..3].copy_from_slice(&[0x01, 0x04, 0x00]);
+        let frame = Frame::new(DataType::SetRequest, &data[..]);
+        assert_eq!(Err(DecodingError::InconsistentData(DataType::SetRequest)), FrameData::parse(frame));
+
+        // Only the setpoint mapped byte is set
+        data[5] = 0x0a;
+        let frame = Frame::new(DataType::SetRequest, &data[..]);
+        let expected = SetRequest { temp: Some(Setpoint(TenthDegreesC(210))), ..SetRequest::default() };
+        assert_eq!(Ok((EMPTY, FrameData::SetRequest(expected))), FrameData::parse(frame));
+
+        // The wide vane is flagged, but zero
+        data[2] = 0x01;
+        let frame = Frame::new(DataType::SetRequest, &data[..]);
+        assert_eq!(Err(DecodingError::InconsistentData(DataType::SetRequest)), FrameData::parse(frame));
+    }
+
+    #[test]
+    fn experimental_humidity_test() {
+        let mut data = [0x03, 0x00, 0x00, 0x0b, 0x00, 0x00, 0xab, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(None, GetInfoResponse::experimental_humidity(&data));
+        data[7] = 101;
+        assert_eq!(None, GetInfoResponse::experimental_humidity(&data));
+        data[7] = 100;
+        assert_eq!(Some(100), GetInfoResponse::experimental_humidity(&data));
+        assert_eq!(None, GetInfoResponse::experimental_humidity(&data[..7]));
+        data[0] = InfoType::Status as u8;
+        assert_eq!(None, GetInfoResponse::experimental_humidity(&data));
+    }
+
+    #[test]
+    fn write_summary_test() {
+        let mut settings = Settings::power_on_defaults();
+        settings.power = Power::On;
+        settings.mode = Mode::Heat;
+        settings.setpoint = Setpoint(TenthDegreesC(215));
+        settings.vane = Vane::Swing;
+
+        #[cfg(feature = "heapless")]
+        {
+            let mut summary = heapless::String::<40>::new();
+            settings.write_summary(&mut summary).unwrap();
+            assert_eq!("ON heat 21.5°C fan:auto vane:swing", summary.as_str());
+
+            let mut short = heapless::String::<8>::new();
+            assert!(settings.write_summary(&mut short).is_err());
+        }
+
+        settings.setpoint = Setpoint(TenthDegreesC(-5));
+        settings.fan = Fan::Other(0x09);
+        let mut summary = std::string::String::new();
+        settings.write_summary(&mut summary).unwrap();
+        assert_eq!("ON heat -0.5°C fan:unknown vane:swing", summary);
+    }
+
+    #[test]
+    #[cfg(feature = "device")]
+    fn set_request_from_settings_test() {
+        let settings = Settings::power_on_defaults();
+        let request = SetRequest::from(&settings);
+        assert_eq!(Some(settings.setpoint), request.temp);
+        assert_eq!(Some(WideVane::Center), request.widevane);
+
+        let mut device = super::super::Device::new();
+        device.settings.power = Power::On;
+        device.settings.fan = Fan::F4;
+        device.handle(&FrameData::ConnectRequest(ConnectRequest));
+        device.handle(&FrameData::SetRequest(request));
+        assert_eq!(settings, device.settings);
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
     fn encode_set_request_flags_test() {
         let mut buf: [u8; 2] = [0x00, 0x00];
         let mut data = SetRequest {
@@ -530,7 +1160,7 @@ mod tests {
             fan: Some(Fan::Auto),
             vane: Some(Vane::Swing),
             widevane: Some(WideVane::LL),
-            temp: Some(Temperature::HalfDegreesCPlusOffset { value: TenthDegreesC(210).encode_as_half_deg_plus_offset() }),
+            temp: Some(Setpoint(TenthDegreesC(210))),
         };
 
         data.encode_flags(&mut buf).unwrap();
@@ -569,6 +1199,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "controller")]
     fn encode_set_request_test() {
         let mut buf: [u8; 16] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
         let expected: [u8; 16] = [
@@ -585,13 +1216,14 @@ mod tests {
             fan: Some(Fan::Auto),
             vane: Some(Vane::Swing),
             widevane: Some(WideVane::LL),
-            temp: Some(Temperature::HalfDegreesCPlusOffset { value: TenthDegreesC(210).encode_as_half_deg_plus_offset() }),
+            temp: Some(Setpoint(TenthDegreesC(210))),
         }.encode(&mut buf);
         assert_eq!(Ok(16), result);
         assert_eq!(expected, buf);
     }
 
     #[test]
+    #[cfg(feature = "device")]
     fn parse_connect_request_test() {
         let data: &[u8] = &[0xca, 0x01];
         let result = FrameData::parse_data_type(FrameData::ConnectRequest, data);
@@ -599,6 +1231,17 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "device")]
+    fn parse_invalid_data_test() {
+        let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x02][..]);
+        assert_eq!(Err(DecodingError::InvalidData(DataType::ConnectRequest)), FrameData::parse(frame));
+
+        let frame = Frame::new(DataType::SetRequest, &[0x01, 0x01, 0x00, 0x01][..]);
+        assert_eq!(Err(DecodingError::ShortData { needed: Some(1) }), FrameData::parse(frame));
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
     fn encode_connect_request_test() {
         let mut buf: [u8; 2] = [0x00, 0x00];
         let expected: [u8; 2] = [0xca, 0x01];
@@ -608,6 +1251,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "controller")]
     fn parse_get_info_response_settings_test() {
         let data: &[u8] = &[
             0x02, 0x00, 0x00, 0x01, 0x01, 0x0f, 0x00, 0x07,
@@ -616,18 +1260,19 @@ mod tests {
 
         let result = GetInfoResponse::decode_settings(data);
 
-        assert_eq!(Ok((EMPTY, GetInfoResponse::Settings {
+        assert_eq!(Ok((EMPTY, GetInfoResponse::Settings(Settings {
             power: Power::On,
             mode: Mode::Heat,
-            setpoint: Temperature::HalfDegreesCPlusOffset { value: 0x94 },
+            setpoint: Setpoint(TenthDegreesC(100)),
             fan: Fan::Auto,
             vane: Vane::Swing,
             widevane: WideVane::Center,
             isee: ISee::Off,
-        })), result);
+        }))), result);
     }
 
     #[test]
+    #[cfg(feature = "controller")]
     fn parse_get_info_response_room_temp_test() {
         let data: &[u8] = &[
             0x03, 0x00, 0x00, 0x0b, 0x00, 0x00, 0xaa, 0x00,
@@ -651,4 +1296,87 @@ mod tests {
             temperature: Temperature::RoomTempMapped{ value: 0x0b },
         })), result2);
     }
+
+    #[test]
+    fn info_type_all_test() {
+        for &info_type in InfoType::ALL.iter() {
+            assert_eq!(Ok(info_type), InfoType::try_from(info_type as u8));
+        }
+        assert!(InfoType::POLLED.iter().all(|t| InfoType::ALL.contains(t)));
+        assert_eq!(GetInfoRequest::new(InfoType::Status), GetInfoRequest::STATUS);
+        assert_eq!(InfoType::Status, GetInfoRequest::STATUS.info_type());
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
+    fn parse_get_info_response_dispatch_test() {
+        let standby: &[u8] = &[
+            0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let frame = Frame::new(DataType::GetInfoResponse, standby);
+        assert_eq!(Ok((standby, FrameData::GetInfoResponse(GetInfoResponse::Unknown))), FrameData::parse(frame));
+
+        let truncated_settings: &[u8] = &[0x02, 0x00, 0x00, 0x01, 0x01, 0x0f];
+        let frame = Frame::new(DataType::GetInfoResponse, truncated_settings);
+        assert_eq!(Err(DecodingError::MalformedInfo(InfoType::Settings)), FrameData::parse(frame));
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
+    fn decode_mode_and_isee_test() {
+        assert_eq!((Mode::Heat, ISee::Off), Settings::decode_mode_and_isee(0x01));
+        assert_eq!((Mode::Auto, ISee::Off), Settings::decode_mode_and_isee(0x08));
+        assert_eq!((Mode::Heat, ISee::On), Settings::decode_mode_and_isee(0x09));
+        assert_eq!((Mode::Cool, ISee::On), Settings::decode_mode_and_isee(0x0b));
+        assert_eq!((Mode::Auto, ISee::On), Settings::decode_mode_and_isee(0x10));
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
+    fn parse_impossible_temperature_test() {
+        use super::super::types::ImpossibleTemperature;
+
+        let below_zero: &[u8] = &[
+            0x03, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x7f, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let frame = Frame::new(DataType::GetInfoResponse, below_zero);
+        assert_eq!(Err(DecodingError::ImpossibleTemperature(ImpossibleTemperature {
+            temperature: Temperature::HalfDegreesCPlusOffset { value: 0x7f },
+            min: 0x80,
+            max: 0xff,
+        })), FrameData::parse(frame));
+
+        let mapped_out_of_range: &[u8] = &[
+            0x02, 0x00, 0x00, 0x01, 0x01, 0x20, 0x00, 0x07,
+            0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let frame = Frame::new(DataType::GetInfoResponse, mapped_out_of_range);
+        assert!(matches!(FrameData::parse(frame), Err(DecodingError::ImpossibleTemperature(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
+    fn parse_get_info_response_faults_test() {
+        let data: &[u8] = &[
+            0x04, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(Ok((EMPTY, GetInfoResponse::Faults {
+            error_code: 0x8000,
+            fault: None,
+        })), GetInfoResponse::decode_faults(data));
+
+        let data: &[u8] = &[
+            0x04, 0x00, 0x00, 0x00, 0x68, 0x40, 0x46, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(Ok((EMPTY, GetInfoResponse::Faults {
+            error_code: 0x6840,
+            fault: Some(FaultCode::new(b'E', b'6')),
+        })), GetInfoResponse::decode_faults(data));
+    }
 }
@@ -0,0 +1,159 @@
+use super::frame::{DataType, FRAME_START};
+
+/// One exchange of a connection handshake: a raw, encoded frame to send, and
+/// the data type byte of the reply that should come back. Raw frames are used
+/// as handshakes can include data types this crate doesn't otherwise know.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HandshakeStep<'a> {
+    pub request: &'a [u8],
+    pub reply_type: u8,
+}
+
+impl HandshakeStep<'static> {
+    /// The standard `ConnectRequest`, answered by a `ConnectResponse`
+    pub const CONNECT: HandshakeStep<'static> = HandshakeStep {
+        request: &[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8],
+        reply_type: DataType::ConnectResponse.as_byte(),
+    };
+
+    /// The extended connect sent by the official WiFi adapters after the
+    /// standard one, answered with data type `0x7b`. Its reply isn't
+    /// understood, but some units are reported to only respond reliably to
+    /// polling once they've seen it, particularly after an adapter has been
+    /// removed.
+    pub const EXTENDED_CONNECT: HandshakeStep<'static> = HandshakeStep {
+        request: &[0xfc, 0x5b, 0x01, 0x30, 0x01, 0xc9, 0xaa],
+        reply_type: 0x7b,
+    };
+}
+
+/// The handshake performed by the official WiFi adapters
+pub const ADAPTER_HANDSHAKE: [HandshakeStep<'static>; 2] = [
+    HandshakeStep::CONNECT,
+    HandshakeStep::EXTENDED_CONNECT,
+];
+
+/// A reply which doesn't match the handshake step it answered
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnexpectedReply {
+    pub expected: u8,
+    pub received: u8,
+}
+
+/// Steps through a connection handshake. This does no I/O: send
+/// `next_request()`, and pass each frame received to `receive()`, until
+/// `is_complete()`. On a timeout, sending `next_request()` again retries the
+/// current step.
+///
+/// ```
+/// use mitsu_ac::protocol::{Handshake, ADAPTER_HANDSHAKE};
+///
+/// let mut handshake = Handshake::new(&ADAPTER_HANDSHAKE);
+///
+/// while let Some(request) = handshake.next_request() {
+///     // Send `request` and wait for the reply, here faked
+///     let reply: &[u8] = match request[1] {
+///         0x5a => &[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54],
+///         _ => &[0xfc, 0x7b, 0x01, 0x30, 0x01, 0x00, 0x53],
+///     };
+///     handshake.receive(reply).unwrap();
+/// }
+///
+/// assert!(handshake.is_complete());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Handshake<'a> {
+    steps: &'a [HandshakeStep<'a>],
+    next: usize,
+}
+
+impl<'a> Handshake<'a> {
+    pub fn new(steps: &'a [HandshakeStep<'a>]) -> Self {
+        Self { steps, next: 0 }
+    }
+
+    /// The frame to send for the current step, or `None` once complete
+    pub fn next_request(&self) -> Option<&'a [u8]> {
+        self.steps.get(self.next).map(|step| step.request)
+    }
+
+    /// Handles a frame received in reply to the current step, moving on to
+    /// the next step if it's the expected reply. Only the frame's data type
+    /// byte is looked at; check its checksum first.
+    ///
+    /// An unexpected reply leaves the handshake at the current step, so the
+    /// request can be retried. Frames received after completion are ignored.
+    pub fn receive(&mut self, frame: &[u8]) -> Result<(), UnexpectedReply> {
+        let step = match self.steps.get(self.next) {
+            Some(step) => step,
+            None => return Ok(()),
+        };
+
+        let received = match frame {
+            [FRAME_START, data_type, ..] => *data_type,
+            _ => 0,
+        };
+
+        if received == step.reply_type {
+            self.next += 1;
+            Ok(())
+        } else {
+            Err(UnexpectedReply { expected: step.reply_type, received })
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next >= self.steps.len()
+    }
+
+    /// Starts again from the first step, e.g. after the link has dropped
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::frame::{verify_checksum, Frame};
+    use super::super::frame_data::FrameData;
+    use super::super::reader::FrameReader;
+
+    #[test]
+    fn steps_test() {
+        for step in ADAPTER_HANDSHAKE.iter() {
+            assert_eq!(Ok(()), verify_checksum(step.request));
+        }
+    }
+
+    #[test]
+    fn extended_connect_reply_test() {
+        let reply = [0xfc, 0x7b, 0x01, 0x30, 0x01, 0x00, 0x53];
+
+        let mut reader = FrameReader::new();
+        assert_eq!(Some(Ok(FrameData::Unknown)), reader.read(&mut reply.iter().copied()));
+
+        let (rest, frame) = Frame::parse(&reply).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(&[0x00][..], frame.data);
+    }
+
+    #[test]
+    fn unexpected_reply_test() {
+        let mut handshake = Handshake::new(&ADAPTER_HANDSHAKE);
+        let set_response = [0xfc, 0x61, 0x01, 0x30, 0x10, 0x00];
+
+        assert_eq!(
+            Err(UnexpectedReply { expected: 0x7a, received: 0x61 }),
+            handshake.receive(&set_response)
+        );
+        assert_eq!(Some(HandshakeStep::CONNECT.request), handshake.next_request());
+
+        assert_eq!(Ok(()), handshake.receive(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]));
+        assert_eq!(Some(HandshakeStep::EXTENDED_CONNECT.request), handshake.next_request());
+        assert!(!handshake.is_complete());
+
+        handshake.reset();
+        assert_eq!(Some(HandshakeStep::CONNECT.request), handshake.next_request());
+    }
+}
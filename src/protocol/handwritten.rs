@@ -0,0 +1,232 @@
+//! Hand-written parsers, used in place of the nom parsers when the `nom`
+//! feature is disabled. They must behave identically, down to the number of
+//! bytes reported as needed when input runs out, so that the same tests pass
+//! against either.
+
+use super::decoding::{ParseError, ParseResult};
+#[cfg(feature = "controller")]
+use super::fault::FaultCode;
+use super::frame::{DataType, Frame, Header, FRAME_START};
+use super::frame_data::*;
+#[cfg(feature = "controller")]
+use super::types::{Power, Temperature, Setpoint, Fan, Vane, WideVane};
+
+/// Reads values from the front of a byte slice
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+// Not every method is needed by every combination of features
+#[allow(dead_code)]
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParseError> {
+        if self.data.len() < n {
+            return Err(ParseError::Incomplete(Some(n)));
+        }
+        let (taken, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<(), ParseError> {
+        self.take(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn be_u16(&mut self) -> Result<u16, ParseError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Expects `tag` next. Input which is a prefix of `tag` is incomplete,
+    /// rather than invalid.
+    fn tag(&mut self, tag: &[u8]) -> Result<(), ParseError> {
+        let available = tag.len().min(self.data.len());
+        if self.data[..available] != tag[..available] {
+            return Err(ParseError::Invalid);
+        }
+        self.skip(tag.len())
+    }
+
+    fn finish<T>(self, value: T) -> ParseResult<'a, T> {
+        Ok((self.data, value))
+    }
+}
+
+impl Frame<&[u8]> {
+    pub(super) fn skip_to_start(data: &[u8]) -> ParseResult<'_, &[u8]> {
+        match data.iter().position(|&b| b == FRAME_START) {
+            Some(start) => {
+                let (skipped, rest) = data.split_at(start);
+                Ok((rest, skipped))
+            },
+            None => Err(ParseError::Incomplete(Some(1))),
+        }
+    }
+
+    pub(super) fn parse_header_and_data(data: &[u8], header: Header) -> ParseResult<'_, (DataType, usize, &[u8])> {
+        let mut r = Reader::new(data);
+        r.tag(&[FRAME_START])?;
+        let data_type = DataType::from_byte(r.u8()?);
+        r.tag(&[header.b3, header.b4])?;
+        let data_len = r.u8()? as usize;
+        let data = r.take(data_len)?;
+        r.finish((data_type, data_len, data))
+    }
+}
+
+#[cfg(feature = "device")]
+impl Parseable for SetRequest {
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(data);
+        r.tag(&[0x01])?;
+        let flags = r.u8()?;
+        let widevane_flag = r.u8()?;
+
+        let power = r.u8()?;
+        let mode = r.u8()?;
+        let temp_mapped = r.u8()?;
+        let fan = r.u8()?;
+        let vane = r.u8()?;
+        r.skip(5)?;
+        let widevane = r.u8()?;
+        let temp_half_deg = r.u8()?;
+        r.skip(1)?;
+
+        let fields = SetRequestFields { power, mode, temp_mapped, fan, vane, widevane, temp_half_deg };
+        r.finish(SetRequest::from_fields(flags, widevane_flag, fields)?)
+    }
+}
+
+#[cfg(feature = "device")]
+impl Parseable for GetInfoRequest {
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(data);
+        let info_type = InfoType::from_byte(r.u8()?);
+        r.skip(GetInfoRequest::padding(data))?;
+        r.finish(GetInfoRequest::new(info_type))
+    }
+}
+
+#[cfg(feature = "device")]
+impl Parseable for ConnectRequest {
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(data);
+        r.tag(&[ConnectRequest::BYTE1, ConnectRequest::BYTE2])?;
+        r.finish(ConnectRequest)
+    }
+}
+
+#[cfg(feature = "controller")]
+impl Parseable for SetResponse {
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(data);
+        r.skip(16)?;
+        r.finish(SetResponse)
+    }
+}
+
+#[cfg(feature = "controller")]
+impl GetInfoResponse {
+    pub(super) fn decode_settings(input: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(input);
+        r.tag(&[InfoType::Settings as u8])?;
+        r.skip(2)?;
+        let power = Power::from_byte(r.u8()?);
+        let (mode, isee) = Settings::decode_mode_and_isee(r.u8()?);
+        let setpoint_mapped = r.u8()?;
+        let fan = Fan::from_byte(r.u8()?);
+        let vane = Vane::from_byte(r.u8()?);
+        r.skip(2)?;
+        let widevane = WideVane::from_byte(r.u8()?);
+        let setpoint = match r.u8()? {
+            0 => Temperature::SetpointMapped { value: setpoint_mapped },
+            value => Temperature::HalfDegreesCPlusOffset { value },
+        };
+        r.skip(4)?;
+
+        r.finish(GetInfoResponse::Settings(Settings {
+            power,
+            mode,
+            setpoint: Setpoint(setpoint.checked()?.celsius_tenths()),
+            fan,
+            vane,
+            widevane,
+            isee,
+        }))
+    }
+
+    pub(super) fn decode_room_temp(input: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(input);
+        r.tag(&[InfoType::RoomTemp as u8])?;
+        r.skip(2)?;
+        let mapped = r.u8()?;
+        r.skip(2)?;
+        let half_deg = r.u8()?;
+        r.skip(9)?;
+
+        let temperature = match half_deg {
+            0 => Temperature::RoomTempMapped { value: mapped },
+            value => Temperature::HalfDegreesCPlusOffset { value },
+        };
+        r.finish(GetInfoResponse::RoomTemperature { temperature: temperature.checked()? })
+    }
+
+    pub(super) fn decode_faults(input: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(input);
+        r.tag(&[InfoType::Type4 as u8])?;
+        r.skip(3)?;
+        let error_code = r.be_u16()?;
+        let fault = FaultCode::from_short_code(r.u8()?);
+        r.skip(9)?;
+        r.finish(GetInfoResponse::Faults { error_code, fault })
+    }
+
+    pub(super) fn decode_timer(input: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(input);
+        r.tag(&[InfoType::Timers as u8])?;
+        r.finish(GetInfoResponse::Unknown)
+    }
+
+    pub(super) fn decode_status(input: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(input);
+        r.tag(&[InfoType::Status as u8])?;
+        r.skip(2)?;
+        let compressor_frequency = r.u8()?;
+        let operating = r.u8()?;
+        r.skip(11)?;
+        r.finish(GetInfoResponse::Status { compressor_frequency, operating })
+    }
+}
+
+#[cfg(feature = "controller")]
+impl Parseable for ConnectResponse {
+    fn parse(data: &[u8]) -> ParseResult<'_, Self> {
+        let mut r = Reader::new(data);
+        let b = r.u8()?;
+        r.finish(ConnectResponse::new(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_test() {
+        let mut r = Reader::new(&[0xfc, 0x01, 0x30, 0x12, 0x34]);
+        assert_eq!(Ok(()), r.tag(&[0xfc]));
+        assert_eq!(Err(ParseError::Invalid), Reader::new(&[0x02]).tag(&[0x01, 0x30]));
+        assert_eq!(Err(ParseError::Incomplete(Some(2))), Reader::new(&[0x01]).tag(&[0x01, 0x30]));
+        assert_eq!(Ok(()), r.tag(&[0x01, 0x30]));
+        assert_eq!(Ok(0x1234), r.be_u16());
+        assert_eq!(Err(ParseError::Incomplete(Some(1))), r.u8());
+    }
+}
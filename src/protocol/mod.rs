@@ -1,10 +1,37 @@
 mod frame;
 mod frame_data;
+mod reader;
+mod parsed;
+mod variant;
+mod handshake;
+#[cfg(feature = "device")]
+mod device;
+#[cfg(feature = "alloc")]
+mod owned;
+#[cfg(feature = "controller")]
+mod template;
+#[cfg(not(feature = "nom"))]
+mod handwritten;
 
 #[macro_use]
 pub mod encoding;
+pub mod decoding;
 pub mod types;
+pub mod fault;
 
-pub use frame::{Frame, FrameParsingError, DataType};
+pub use frame::{Frame, DataType, Header, MAX_DATA_LEN, DEFAULT_MAX_DATA_LEN, ScanStats, Checksum, SumChecksum, checksum, checksum_with_header, verify_checksum, fix_checksum};
+#[cfg(feature = "dump")]
+pub(crate) use frame::HEADER_LEN;
 pub use frame_data::*;
-pub use encoding::Encodable;
+pub use parsed::ParsedFrame;
+pub use variant::{ProtocolVariant, Cn105};
+#[cfg(feature = "device")]
+pub use device::Device;
+#[cfg(feature = "controller")]
+pub use template::SetRequestTemplate;
+pub use handshake::{Handshake, HandshakeStep, UnexpectedReply, ADAPTER_HANDSHAKE};
+pub use reader::{FrameReader, Frames, MAX_FRAME_LEN};
+#[cfg(feature = "alloc")]
+pub use owned::{OwnedFrameData, RxBuffer, encode_to_vec, decode_hex_frame, HexFrameError};
+pub use encoding::{Encodable, EncodeBuffer, ByteSink, encode_all, encode_uninit};
+pub use decoding::{DecodingError, DecodingResult, UnknownValue};
@@ -0,0 +1,280 @@
+use alloc::vec::Vec;
+
+use super::frame::{Frame, DataType, FRAME_START, HEADER_LEN};
+use super::frame_data::{FrameData, GetInfoResponse};
+use super::encoding::{Encodable, EncodingError, SizedEncoding};
+use super::decoding::DecodingError;
+
+/// `FrameData` which owns a copy of the raw payload of any frame it couldn't
+/// decode, so unknown frames can be logged or forwarded after the receive
+/// buffer has been reused
+///
+/// ```
+/// use mitsu_ac::protocol::{Frame, DataType, OwnedFrameData};
+///
+/// let (_, frame) = Frame::parse(&[0xfc, 0x62, 0x01, 0x30, 0x02, 0x09, 0x01, 0x61]).unwrap();
+///
+/// match OwnedFrameData::parse(frame).unwrap() {
+///     OwnedFrameData::Unknown { data_type, data } => {
+///         assert_eq!(DataType::GetInfoResponse, data_type);
+///         assert_eq!(vec![0x09, 0x01], data);
+///     },
+///     OwnedFrameData::Known(_) => panic!("Expected an unknown frame"),
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum OwnedFrameData {
+    Known(FrameData),
+    /// A frame whose data type, or `InfoType` for a `GetInfoResponse`, isn't
+    /// understood, along with its payload. An unknown data type keeps its
+    /// byte, as `DataType::Unknown`.
+    Unknown { data_type: DataType, data: Vec<u8> },
+}
+
+impl OwnedFrameData {
+    /// Parses out the data from a given `Frame`, keeping a copy of the payload
+    /// if it decodes as `FrameData::Unknown` or `GetInfoResponse::Unknown`
+    pub fn parse(frame: Frame<&[u8]>) -> Result<Self, DecodingError> {
+        let (_, data) = FrameData::parse(frame)?;
+        Ok(match data {
+            FrameData::Unknown | FrameData::GetInfoResponse(GetInfoResponse::Unknown) =>
+                OwnedFrameData::Unknown { data_type: frame.data_type, data: frame.data.to_vec() },
+            data => OwnedFrameData::Known(data),
+        })
+    }
+}
+
+impl From<FrameData> for OwnedFrameData {
+    fn from(data: FrameData) -> Self {
+        OwnedFrameData::Known(data)
+    }
+}
+
+/// A growable receive buffer. Bytes are appended as they arrive, and whole
+/// frames taken off the front; unlike `FrameReader` there's no limit on how
+/// much can be buffered before it's decoded.
+///
+/// ```
+/// # #[cfg(feature = "controller")] {
+/// use mitsu_ac::protocol::{RxBuffer, OwnedFrameData, FrameData, ConnectResponse};
+///
+/// let mut rx = RxBuffer::new();
+/// rx.extend(&[0x42, 0x00, 0xfc, 0x7a, 0x01]);
+/// assert_eq!(None, rx.next_frame());
+///
+/// rx.extend(&[0x30, 0x01, 0x00, 0x54]);
+/// assert_eq!(
+///     Some(Ok(OwnedFrameData::Known(FrameData::ConnectResponse(ConnectResponse::new(0))))),
+///     rx.next_frame()
+/// );
+/// assert!(rx.is_empty());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RxBuffer {
+    buf: Vec<u8>,
+}
+
+impl RxBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { buf: Vec::with_capacity(capacity) }
+    }
+
+    /// Appends received bytes
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Takes the next frame off the front of the buffer, skipping any junk
+    /// before it. Returns `None` until a whole frame has been received.
+    ///
+    /// A frame with a bad header or checksum is dropped and its error
+    /// returned; call again to carry on with the following bytes.
+    pub fn next_frame(&mut self) -> Option<Result<OwnedFrameData, DecodingError>> {
+        match self.buf.iter().position(|&b| b == FRAME_START) {
+            Some(start) => { self.buf.drain(..start); },
+            None => {
+                self.buf.clear();
+                return None;
+            },
+        }
+
+        let (consumed, result) = match Frame::parse(&self.buf) {
+            Err(DecodingError::Incomplete { .. }) => return None,
+            Ok((rest, frame)) => (self.buf.len() - rest.len(), OwnedFrameData::parse(frame)),
+            Err(e @ DecodingError::InvalidChecksum { data_len, .. }) => (HEADER_LEN + data_len + 1, Err(e)),
+            Err(e) => (1, Err(e)),
+        };
+
+        self.buf.drain(..consumed.min(self.buf.len()));
+        Some(result)
+    }
+
+    /// The bytes received but not yet taken as frames
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+impl SizedEncoding for Vec<u8> {
+    fn length(&self) -> usize { self.len() }
+}
+
+impl Encodable for Vec<u8> {
+    fn encode(&self, into: &mut [u8]) -> Result<usize, EncodingError> {
+        self.as_slice().encode(into)
+    }
+}
+
+/// Errors from `decode_hex_frame`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum HexFrameError {
+    /// The character at byte offset `position` of the input isn't a hex
+    /// digit or a separator
+    InvalidCharacter { position: usize },
+    /// The run of hex digits starting at byte offset `position` has an odd
+    /// number of digits, so doesn't split into bytes
+    OddDigits { position: usize },
+    /// The bytes don't decode as a frame
+    Decoding(DecodingError),
+    /// There are `len` bytes left over after the frame
+    TrailingBytes { len: usize },
+}
+
+impl From<DecodingError> for HexFrameError {
+    fn from(e: DecodingError) -> Self {
+        HexFrameError::Decoding(e)
+    }
+}
+
+/// Decodes one frame written out in hex, such as a line pasted from a
+/// capture, returning the frame with its raw data and the decoded data. Bytes
+/// may be run together or separated by whitespace, commas or colons, and may
+/// be prefixed with `0x`.
+///
+/// ```
+/// # #[cfg(feature = "controller")] {
+/// use mitsu_ac::protocol::{decode_hex_frame, DataType, FrameData, ConnectResponse};
+///
+/// let (frame, data) = decode_hex_frame("fc 7a 01 30 01 00 54").unwrap();
+/// assert_eq!(DataType::ConnectResponse, frame.data_type);
+/// assert_eq!(vec![0x00], frame.data);
+/// assert_eq!(FrameData::ConnectResponse(ConnectResponse::new(0)), data);
+///
+/// assert!(decode_hex_frame("0xfc,0x7a,0x01,0x30,0x01,0x00,0x54").is_ok());
+/// # }
+/// ```
+pub fn decode_hex_frame(hex: &str) -> Result<(Frame<Vec<u8>>, FrameData), HexFrameError> {
+    let bytes = decode_hex(hex)?;
+    let (rest, frame) = Frame::parse_complete(&bytes)?;
+    if !rest.is_empty() {
+        return Err(HexFrameError::TrailingBytes { len: rest.len() });
+    }
+    let (_, data) = FrameData::parse(frame)?;
+    Ok((Frame { data_type: frame.data_type, header: frame.header, data: frame.data.to_vec() }, data))
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, HexFrameError> {
+    let is_separator = |c: char| c.is_whitespace() || c == ',' || c == ':';
+    let mut bytes = Vec::new();
+    for token in hex.split(is_separator) {
+        let digits = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+        let position = digits.as_ptr() as usize - hex.as_ptr() as usize;
+
+        if let Some(i) = digits.find(|c: char| !c.is_ascii_hexdigit()) {
+            return Err(HexFrameError::InvalidCharacter { position: position + i });
+        }
+        if digits.len() % 2 != 0 {
+            return Err(HexFrameError::OddDigits { position });
+        }
+        let digit = |b: u8| (b as char).to_digit(16).unwrap_or(0) as u8;
+        bytes.extend(digits.as_bytes().chunks(2).map(|pair| digit(pair[0]) << 4 | digit(pair[1])));
+    }
+    Ok(bytes)
+}
+
+/// Encodes `value` into a newly allocated `Vec` of exactly the right length
+///
+/// ```
+/// use mitsu_ac::protocol::{encode_to_vec, Frame, DataType};
+///
+/// let frame = Frame::new(DataType::ConnectRequest, &[0xca, 0x01][..]);
+/// assert_eq!(Ok(vec![0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8]), encode_to_vec(&frame));
+/// ```
+pub fn encode_to_vec<T: Encodable>(value: &T) -> Result<Vec<u8>, EncodingError> {
+    let mut buf = alloc::vec![0; value.length()];
+    let len = value.encode(&mut buf)?;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+#[cfg(all(test, feature = "controller"))]
+mod tests {
+    use super::*;
+    use super::super::frame_data::ConnectResponse;
+
+    #[test]
+    fn parse_unknown_test() {
+        let (_, frame) = Frame::parse(&[0xfc, 0x99, 0x01, 0x30, 0x03, 0x01, 0x02, 0x03, 0x2d]).unwrap();
+        assert_eq!(
+            Ok(OwnedFrameData::Unknown { data_type: DataType::Unknown(0x99), data: alloc::vec![0x01, 0x02, 0x03] }),
+            OwnedFrameData::parse(frame)
+        );
+
+        let frame = Frame::new(DataType::ConnectResponse, &[0x00][..]);
+        assert_eq!(
+            Ok(OwnedFrameData::Known(FrameData::ConnectResponse(ConnectResponse::new(0)))),
+            OwnedFrameData::parse(frame)
+        );
+    }
+
+    #[test]
+    fn rx_buffer_errors_test() {
+        let mut rx = RxBuffer::new();
+        rx.extend(&[0xfc, 0x7a, 0x02, 0x30]);
+        rx.extend(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x55]);
+        rx.extend(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]);
+
+        assert_eq!(Some(Err(DecodingError::BadHeader)), rx.next_frame());
+        assert!(matches!(rx.next_frame(), Some(Err(DecodingError::InvalidChecksum { .. }))));
+        assert!(matches!(rx.next_frame(), Some(Ok(OwnedFrameData::Known(_)))));
+        assert_eq!(None, rx.next_frame());
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn rx_buffer_drops_junk_test() {
+        let mut rx = RxBuffer::new();
+        rx.extend(&[0x00, 0x01, 0x02]);
+        assert_eq!(None, rx.next_frame());
+        assert!(rx.is_empty());
+
+        rx.extend(&[0x00, 0xfc, 0x7a]);
+        assert_eq!(None, rx.next_frame());
+        assert_eq!(&[0xfc, 0x7a], rx.as_slice());
+    }
+
+    #[test]
+    fn decode_hex_frame_errors_test() {
+        assert_eq!(Err(HexFrameError::InvalidCharacter { position: 4 }), decode_hex_frame("fc, xa"));
+        assert_eq!(Err(HexFrameError::OddDigits { position: 5 }), decode_hex_frame("fc 0x7a0"));
+        assert_eq!(Err(HexFrameError::Decoding(DecodingError::Truncated { needed: Some(1) })), decode_hex_frame("fc7a013001"));
+        assert_eq!(Err(HexFrameError::TrailingBytes { len: 1 }), decode_hex_frame("fc 7a 01 30 01 00 54 00"));
+    }
+}
@@ -0,0 +1,111 @@
+use core::cell::Cell;
+
+use super::frame::{Frame, DataType};
+use super::frame_data::{FrameData, InfoType};
+use super::decoding::{DecodingError, DecodingResult};
+
+/// A received frame whose header and checksum have been checked once, and
+/// whose data is decoded on first use and then cached. Useful when several
+/// parts of an application look at the same inbound frame.
+///
+/// ```
+/// # #[cfg(feature = "controller")] {
+/// use mitsu_ac::protocol::{ParsedFrame, DataType, FrameData, ConnectResponse};
+///
+/// let (_, parsed) = ParsedFrame::parse(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]).unwrap();
+/// assert_eq!(DataType::ConnectResponse, parsed.data_type());
+///
+/// assert_eq!(Ok(FrameData::ConnectResponse(ConnectResponse::new(0))), parsed.data());
+/// assert_eq!(Ok(FrameData::ConnectResponse(ConnectResponse::new(0))), parsed.data());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ParsedFrame<'a> {
+    raw: &'a [u8],
+    frame: Frame<&'a [u8]>,
+    data: Cell<Option<Result<FrameData, DecodingError>>>,
+}
+
+impl<'a> ParsedFrame<'a> {
+    /// Parses a frame from the start of `bytes`, as `Frame::parse` does
+    pub fn parse(bytes: &'a [u8]) -> DecodingResult<'a, Self> {
+        let (rest, frame) = Frame::parse(bytes)?;
+        let raw = &bytes[..bytes.len() - rest.len()];
+        Ok((rest, Self { raw, frame, data: Cell::new(None) }))
+    }
+
+    /// The frame's encoded bytes, header and checksum included
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    pub fn frame(&self) -> Frame<&'a [u8]> {
+        self.frame
+    }
+
+    pub fn data_type(&self) -> DataType {
+        self.frame.data_type
+    }
+
+    /// The `InfoType` of a `GetInfoRequest` or `GetInfoResponse`, read
+    /// without decoding the rest of the data
+    pub fn info_type(&self) -> Option<InfoType> {
+        match self.frame.data_type {
+            DataType::GetInfoRequest | DataType::GetInfoResponse =>
+                self.frame.data.first().map(|&b| InfoType::from_byte(b)),
+            _ => None,
+        }
+    }
+
+    /// The decoded data, as from `FrameData::parse`. Decoded on the first
+    /// call only.
+    pub fn data(&self) -> Result<FrameData, DecodingError> {
+        match self.data.get() {
+            Some(data) => data,
+            None => {
+                let data = FrameData::parse(self.frame).map(|(_, data)| data);
+                self.data.set(Some(data));
+                data
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let bytes: &[u8] = &[0xfc, 0x42, 0x01, 0x30, 0x01, 0x03, 0x89, 0x00];
+        let (rest, parsed) = ParsedFrame::parse(bytes).unwrap();
+
+        assert_eq!(&[0x00], rest);
+        assert_eq!(&bytes[..7], parsed.raw());
+        assert_eq!(&[0x03], parsed.frame().data);
+        assert_eq!(Some(InfoType::RoomTemp), parsed.info_type());
+        assert_eq!(None, parsed.data.get());
+    }
+
+    #[test]
+    fn uses_cache_test() {
+        let bytes: &[u8] = &[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54];
+        let (_, parsed) = ParsedFrame::parse(bytes).unwrap();
+
+        // A cached result is returned without decoding the frame again
+        parsed.data.set(Some(Ok(FrameData::Unknown)));
+        assert_eq!(Ok(FrameData::Unknown), parsed.data());
+    }
+
+    #[test]
+    #[cfg(feature = "controller")]
+    fn caches_errors_test() {
+        let bytes: &[u8] = &[0xfc, 0x62, 0x01, 0x30, 0x02, 0x02, 0x00, 0x69];
+        let (_, parsed) = ParsedFrame::parse(bytes).unwrap();
+
+        let expected = Err(DecodingError::MalformedInfo(InfoType::Settings));
+        assert_eq!(expected, parsed.data());
+        assert_eq!(Some(expected), parsed.data.get());
+        assert_eq!(expected, parsed.data());
+    }
+}
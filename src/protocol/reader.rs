@@ -0,0 +1,278 @@
+use super::frame::{Frame, Header, FRAME_START, HEADER_LEN, MAX_DATA_LEN, DEFAULT_MAX_DATA_LEN};
+use super::frame_data::FrameData;
+use super::decoding::DecodingError;
+
+/// The longest possible frame: a header, `MAX_DATA_LEN` bytes of data and a
+/// checksum
+pub const MAX_FRAME_LEN: usize = HEADER_LEN + MAX_DATA_LEN + 1;
+
+/// Decodes frames from bytes arriving one at a time, such as from a UART FIFO
+/// drained through an `Iterator<Item = u8>`, without needing the received
+/// bytes in a slice first. Junk between frames is skipped.
+///
+/// Each decoded frame is returned as an owned `FrameData`, so the reader's
+/// internal buffer can be reused straight away.
+///
+/// ```
+/// # #[cfg(feature = "controller")] {
+/// use mitsu_ac::protocol::{FrameReader, FrameData, ConnectResponse};
+///
+/// let rx = [0x42, 0x00, 0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54];
+///
+/// let mut reader = FrameReader::new();
+/// let mut frames = reader.frames(rx.iter().copied());
+///
+/// assert_eq!(
+///     Some(Ok(FrameData::ConnectResponse(ConnectResponse::new(0)))),
+///     frames.next()
+/// );
+/// assert_eq!(None, frames.next());
+/// # }
+/// ```
+///
+/// A frame split across several serial reads is held inside the reader until
+/// the rest of it arrives, so each read's bytes can be passed in as they
+/// come without being joined up first:
+///
+/// ```
+/// use mitsu_ac::protocol::FrameReader;
+///
+/// let mut reader = FrameReader::new();
+///
+/// let first_read = [0xfc, 0x7a, 0x01];
+/// assert_eq!(None, reader.read(&mut first_read.iter().copied()));
+///
+/// let second_read = [0x30, 0x01, 0x00, 0x54];
+/// assert!(reader.read(&mut second_read.iter().copied()).unwrap().is_ok());
+/// ```
+#[derive(Clone)]
+pub struct FrameReader {
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    header: Header,
+    max_data_len: usize,
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self::with_header(Header::CN105)
+    }
+
+    /// A reader for frames with the given header bytes
+    pub fn with_header(header: Header) -> Self {
+        Self {
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            header,
+            max_data_len: DEFAULT_MAX_DATA_LEN,
+        }
+    }
+
+    /// Accepts frames with up to `max_data_len` bytes of data, rather than
+    /// `DEFAULT_MAX_DATA_LEN`. Anything over `MAX_DATA_LEN` is treated as
+    /// `MAX_DATA_LEN`.
+    pub fn with_max_data_len(self, max_data_len: usize) -> Self {
+        Self { max_data_len: max_data_len.min(MAX_DATA_LEN), ..self }
+    }
+
+    /// Adds a received byte. Returns the decoded frame once its last byte has
+    /// been pushed, or an error if the bytes so far can't form a frame.
+    ///
+    /// After an error the reader resynchronises on the next frame start byte
+    /// by itself, so it's fine to keep pushing bytes.
+    pub fn push(&mut self, byte: u8) -> Option<Result<FrameData, DecodingError>> {
+        if self.len == 0 && byte != FRAME_START {
+            return None;
+        }
+
+        self.buf[self.len] = byte;
+        self.len += 1;
+
+        match self.parse() {
+            Err(DecodingError::Incomplete { .. }) => None,
+            Ok(frame) => {
+                let result = FrameData::parse(frame).map(|(_, data)| data);
+                self.len = 0;
+                Some(result)
+            },
+            Err(DecodingError::BadHeader) => {
+                self.resync();
+                Some(Err(DecodingError::BadHeader))
+            },
+            Err(e) => {
+                self.len = 0;
+                Some(Err(e))
+            },
+        }
+    }
+
+    /// Pulls bytes from `bytes` until a frame has been decoded or an error
+    /// found, or `bytes` runs out. A partially received frame is kept until
+    /// the next call.
+    pub fn read<I>(&mut self, bytes: &mut I) -> Option<Result<FrameData, DecodingError>>
+        where I: Iterator<Item = u8>
+    {
+        bytes.find_map(|byte| self.push(byte))
+    }
+
+    /// Iterates over the frames decoded from `bytes`
+    pub fn frames<I>(&mut self, bytes: I) -> Frames<'_, I> where I: Iterator<Item = u8> {
+        Frames { reader: self, bytes }
+    }
+
+    /// Discards any partially received frame
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// The number of bytes of a partially received frame being held
+    pub fn buffered(&self) -> usize {
+        self.len
+    }
+
+    fn parse(&self) -> Result<Frame<&[u8]>, DecodingError> {
+        Frame::parse_with_limit(&self.buf[..self.len], self.header, self.max_data_len).map(|(_, frame)| frame)
+    }
+
+    // Drops the start of a frame with a bad header, keeping anything from the
+    // next frame start byte on, as long as that could still be a frame
+    fn resync(&mut self) {
+        loop {
+            let next_start = self.buf[1..self.len].iter().position(|&b| b == FRAME_START);
+            match next_start {
+                Some(i) => {
+                    self.buf.copy_within(1 + i..self.len, 0);
+                    self.len -= 1 + i;
+                },
+                None => {
+                    self.len = 0;
+                    return;
+                },
+            }
+
+            if self.parse().err() != Some(DecodingError::BadHeader) {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for FrameReader {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FrameReader")
+            .field("buffered", &&self.buf[..self.len])
+            .field("header", &self.header)
+            .field("max_data_len", &self.max_data_len)
+            .finish()
+    }
+}
+
+/// An iterator over the frames decoded from a byte iterator, created by
+/// `FrameReader::frames`
+#[derive(Debug)]
+pub struct Frames<'r, I> {
+    reader: &'r mut FrameReader,
+    bytes: I,
+}
+
+impl<I> Iterator for Frames<'_, I> where I: Iterator<Item = u8> {
+    type Item = Result<FrameData, DecodingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read(&mut self.bytes)
+    }
+}
+
+#[cfg(all(test, feature = "controller"))]
+mod tests {
+    use super::*;
+    use super::super::frame::DataType;
+    use super::super::frame_data::ConnectResponse;
+
+    const CONNECT_RESPONSE: [u8; 7] = [0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54];
+
+    #[test]
+    fn push_test() {
+        let mut reader = FrameReader::new();
+        for &b in &CONNECT_RESPONSE[..6] {
+            assert_eq!(None, reader.push(b));
+        }
+        assert_eq!(6, reader.buffered());
+        assert_eq!(
+            Some(Ok(FrameData::ConnectResponse(ConnectResponse::new(0)))),
+            reader.push(CONNECT_RESPONSE[6])
+        );
+        assert_eq!(0, reader.buffered());
+    }
+
+    #[test]
+    fn split_across_reads_test() {
+        let mut reader = FrameReader::new();
+        let mut first = CONNECT_RESPONSE[..3].iter().copied();
+        let mut second = CONNECT_RESPONSE[3..].iter().copied();
+
+        assert_eq!(None, reader.read(&mut first));
+        assert!(reader.read(&mut second).unwrap().is_ok());
+    }
+
+    #[test]
+    fn resync_test() {
+        // A frame start byte followed by a bad header, hiding the start of a
+        // real frame
+        let rx = [0xfc, 0x7a, 0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54, 0xfc, 0x7a];
+        let mut reader = FrameReader::new();
+        let mut frames = reader.frames(rx.iter().copied());
+
+        assert_eq!(Some(Err(DecodingError::BadHeader)), frames.next());
+        assert_eq!(Some(Ok(FrameData::ConnectResponse(ConnectResponse::new(0)))), frames.next());
+        assert_eq!(None, frames.next());
+        assert_eq!(2, reader.buffered());
+
+        // The next frame start byte is itself the start of a bad header
+        let mut reader = FrameReader::new();
+        let rx = [0xfc, 0xfc, 0x01, 0x31];
+        assert_eq!(Some(Err(DecodingError::BadHeader)), reader.read(&mut rx.iter().copied()));
+        assert_eq!(0, reader.buffered());
+    }
+
+    #[test]
+    fn max_data_len_test() {
+        // A corrupted length byte, followed straight away by a real frame
+        let mut rx = [0u8; 12];
+        rx[..5].copy_from_slice(&[0xfc, 0x7a, 0x01, 0x30, 0xf1]);
+        rx[5..].copy_from_slice(&CONNECT_RESPONSE);
+
+        let mut reader = FrameReader::new();
+        let mut frames = reader.frames(rx.iter().copied());
+        assert_eq!(Some(Err(DecodingError::BadHeader)), frames.next());
+        assert_eq!(Some(Ok(FrameData::ConnectResponse(ConnectResponse::new(0)))), frames.next());
+
+        let mut reader = FrameReader::new().with_max_data_len(0xf1);
+        assert_eq!(None, reader.read(&mut rx.iter().copied()));
+        assert_eq!(12, reader.buffered());
+    }
+
+    #[test]
+    fn invalid_checksum_test() {
+        let mut rx = CONNECT_RESPONSE;
+        rx[6] = 0x55;
+        let mut reader = FrameReader::new();
+
+        assert_eq!(
+            Some(Err(DecodingError::InvalidChecksum {
+                data_type: DataType::ConnectResponse,
+                data_len: 1,
+                calculated: 0x54,
+                received: 0x55,
+            })),
+            reader.read(&mut rx.iter().copied())
+        );
+        assert_eq!(0, reader.buffered());
+    }
+}
@@ -0,0 +1,164 @@
+use super::encoding::{Encodable, OneByteEncodable};
+use super::frame::{Frame, HEADER_LEN};
+use super::frame_data::{FrameData, SetRequest};
+use super::types::{Fan, Mode, Power, Setpoint, Vane, WideVane};
+
+// Offsets into the encoded frame, following the layout documented on
+// `SetRequest`
+const FLAGS: usize = HEADER_LEN + 1;
+const WIDEVANE_FLAG: usize = HEADER_LEN + 2;
+const POWER: usize = HEADER_LEN + 3;
+const MODE: usize = HEADER_LEN + 4;
+const TEMP_MAPPED: usize = HEADER_LEN + 5;
+const FAN: usize = HEADER_LEN + 6;
+const VANE: usize = HEADER_LEN + 7;
+const WIDEVANE: usize = HEADER_LEN + 13;
+const TEMP_HALF_DEG: usize = HEADER_LEN + 14;
+const CHECKSUM: usize = HEADER_LEN + 16;
+
+/// An encoded `SetRequest` frame which is patched in place. Each change
+/// writes only the bytes it affects and adjusts the checksum to match,
+/// rather than encoding the whole frame again, which suits tight control
+/// loops on slow parts.
+///
+/// ```
+/// use mitsu_ac::protocol::{SetRequestTemplate, SetRequest, Frame, FrameData, Encodable};
+/// use mitsu_ac::protocol::types::{Power, Setpoint, TenthDegreesC};
+///
+/// let mut template = SetRequestTemplate::new();
+/// template.power(Power::On).temperature(Setpoint::new(TenthDegreesC(215)).unwrap());
+///
+/// let request = SetRequest {
+///     power: Some(Power::On),
+///     temp: Setpoint::new(TenthDegreesC(215)).ok(),
+///     ..SetRequest::default()
+/// };
+/// let mut buf = [0u8; 22];
+/// Frame::from(FrameData::SetRequest(request)).encode(&mut buf).unwrap();
+///
+/// assert_eq!(&buf, template.as_bytes());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SetRequestTemplate {
+    buf: [u8; Self::LENGTH],
+}
+
+impl SetRequestTemplate {
+    /// The length of an encoded `SetRequest` frame
+    pub const LENGTH: usize = HEADER_LEN + 16 + 1;
+
+    /// A template changing nothing
+    pub fn new() -> Self {
+        Self::from_request(&SetRequest::default())
+    }
+
+    /// A template starting out as `request`
+    pub fn from_request(request: &SetRequest) -> Self {
+        let mut buf = [0u8; Self::LENGTH];
+        // Can't fail, as the buffer is exactly a SetRequest frame long
+        let _ = Frame::from(FrameData::SetRequest(*request)).encode(&mut buf);
+        Self { buf }
+    }
+
+    pub fn power(&mut self, power: Power) -> &mut Self {
+        self.set_flagged(FLAGS, 0b00000001, POWER, power.encoded_as_byte())
+    }
+
+    pub fn mode(&mut self, mode: Mode) -> &mut Self {
+        self.set_flagged(FLAGS, 0b00000010, MODE, mode.encoded_as_byte())
+    }
+
+    pub fn temperature(&mut self, setpoint: Setpoint) -> &mut Self {
+        let tenths = setpoint.celsius_tenths();
+        self.patch(TEMP_MAPPED, tenths.encode_as_setpoint_mapped());
+        self.set_flagged(FLAGS, 0b00000100, TEMP_HALF_DEG, tenths.encode_as_half_deg_plus_offset())
+    }
+
+    pub fn fan(&mut self, fan: Fan) -> &mut Self {
+        self.set_flagged(FLAGS, 0b00001000, FAN, fan.encoded_as_byte())
+    }
+
+    pub fn vane(&mut self, vane: Vane) -> &mut Self {
+        self.set_flagged(FLAGS, 0b00010000, VANE, vane.encoded_as_byte())
+    }
+
+    pub fn widevane(&mut self, widevane: WideVane) -> &mut Self {
+        self.set_flagged(WIDEVANE_FLAG, 0b00000001, WIDEVANE, widevane.encoded_as_byte())
+    }
+
+    /// Unflags every setting, so that the frame changes nothing. The value
+    /// bytes are left as they were.
+    pub fn clear(&mut self) -> &mut Self {
+        self.patch(FLAGS, 0);
+        self.patch(WIDEVANE_FLAG, 0);
+        self
+    }
+
+    /// The encoded frame, ready to send
+    pub fn as_bytes(&self) -> &[u8; Self::LENGTH] {
+        &self.buf
+    }
+
+    fn set_flagged(&mut self, flags: usize, bit: u8, index: usize, value: u8) -> &mut Self {
+        self.patch(flags, self.buf[flags] | bit);
+        self.patch(index, value);
+        self
+    }
+
+    // The checksum is 0xfc less the sum of every other byte, so it moves by
+    // the opposite of each byte's change
+    fn patch(&mut self, index: usize, value: u8) {
+        let old = core::mem::replace(&mut self.buf[index], value);
+        self.buf[CHECKSUM] = self.buf[CHECKSUM].wrapping_add(old).wrapping_sub(value);
+    }
+}
+
+impl Default for SetRequestTemplate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::frame::verify_checksum;
+    use super::super::types::TenthDegreesC;
+
+    #[test]
+    fn patch_test() {
+        let mut template = SetRequestTemplate::new();
+        template
+            .mode(Mode::Cool)
+            .temperature(Setpoint::new(TenthDegreesC(240)).unwrap())
+            .temperature(Setpoint::new(TenthDegreesC(180)).unwrap());
+        assert_eq!(Ok(()), verify_checksum(template.as_bytes()));
+
+        template.clear();
+        assert_eq!(SetRequestTemplate::new().as_bytes()[..POWER], template.as_bytes()[..POWER]);
+        assert_eq!(Ok(()), verify_checksum(template.as_bytes()));
+    }
+
+    #[test]
+    #[cfg(feature = "device")]
+    fn parse_patched_test() {
+        let mut template = SetRequestTemplate::new();
+        template
+            .fan(Fan::F3)
+            .vane(Vane::Swing)
+            .widevane(WideVane::RR)
+            .temperature(Setpoint::new(TenthDegreesC(180)).unwrap());
+
+        let (_, frame) = Frame::parse(template.as_bytes()).unwrap();
+        assert_eq!(
+            Ok(FrameData::SetRequest(SetRequest {
+                temp: Setpoint::new(TenthDegreesC(180)).ok(),
+                fan: Some(Fan::F3),
+                vane: Some(Vane::Swing),
+                widevane: Some(WideVane::RR),
+                ..SetRequest::default()
+            })),
+            FrameData::parse_exact(frame)
+        );
+    }
+}
@@ -1,119 +1,482 @@
+use core::convert::TryFrom;
+use core::str::FromStr;
+
 use super::encoding::*;
-use enum_repr::EnumRepr;
+use super::decoding::UnknownValue;
 
-#[EnumRepr(type="u8")]
-#[derive(Debug, Eq, PartialEq)]
-pub enum Power {
-    Off = 0,
-    On = 1,
+/// A string which isn't the name of any value of the named type
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnknownName {
+    pub type_name: &'static str,
 }
 
-impl OneByteEncodable for Power {
-    fn encoded_as_byte(&self) -> u8 {
-        self.repr()
+/// Declares a one-byte setting enum. Bytes which don't match a known variant
+/// decode to `Other` with `from_byte`, and encode back to the same byte, so
+/// values from newer firmware pass through unmodified. `TryFrom<u8>` is the
+/// strict alternative, rejecting them instead.
+///
+/// Each variant also has a conventional upper-case name, as used by other
+/// CN105 projects, for `as_str` and `FromStr`.
+macro_rules! setting_enum {
+    ( $name:ident { $( $variant:ident = $value:literal => $str:literal ),* $(,)? } ) => {
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+        #[non_exhaustive]
+        pub enum $name {
+            $( $variant, )*
+            /// A value this crate doesn't know about
+            Other(u8),
+        }
+
+        impl $name {
+            pub fn from_byte(byte: u8) -> Self {
+                Self::try_from(byte).unwrap_or($name::Other(byte))
+            }
+
+            /// The conventional name of the value, or `"UNKNOWN"` for `Other`
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $( $name::$variant => $str, )*
+                    $name::Other(_) => "UNKNOWN",
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = UnknownName;
+
+            /// Parses a conventional name, ignoring case
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $( if s.eq_ignore_ascii_case($str) { return Ok($name::$variant); } )*
+                Err(UnknownName { type_name: stringify!($name) })
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = UnknownValue;
+
+            fn try_from(byte: u8) -> Result<Self, Self::Error> {
+                match byte {
+                    $( $value => Ok($name::$variant), )*
+                    _ => Err(UnknownValue { type_name: stringify!($name), byte }),
+                }
+            }
+        }
+
+        impl OneByteEncodable for $name {
+            fn encoded_as_byte(&self) -> u8 {
+                match *self {
+                    $( $name::$variant => $value, )*
+                    $name::Other(byte) => byte,
+                }
+            }
+        }
     }
 }
 
+setting_enum!(Power {
+    Off = 0x00 => "OFF",
+    On  = 0x01 => "ON",
+});
+
 one_byte_encodable_enum!(Power, Mode, Fan, Vane, WideVane);
 
-#[EnumRepr(type="u8")]
-#[derive(Debug, Eq, PartialEq)]
-pub enum Mode {
-    Heat = 0x01,
-    Dry  = 0x02,
-    Cool = 0x03,
-    Fan  = 0x07,
-    Auto = 0x08,
+setting_enum!(Mode {
+    Heat = 0x01 => "HEAT",
+    Dry  = 0x02 => "DRY",
+    Cool = 0x03 => "COOL",
+    Fan  = 0x07 => "FAN",
+    Auto = 0x08 => "AUTO",
+});
+
+setting_enum!(Fan {
+    Auto  = 0x00 => "AUTO",
+    Quiet = 0x01 => "QUIET",
+    F1    = 0x02 => "1",
+    F2    = 0x03 => "2",
+    F3    = 0x05 => "3",
+    F4    = 0x06 => "4",
+});
+
+setting_enum!(Vane {
+    Auto  = 0x00 => "AUTO",
+    V1    = 0x01 => "1",
+    V2    = 0x02 => "2",
+    V3    = 0x03 => "3",
+    V4    = 0x04 => "4",
+    V5    = 0x05 => "5",
+    Swing = 0x07 => "SWING",
+});
+
+setting_enum!(WideVane {
+    LL     = 0x01 => "<<",
+    L      = 0x02 => "<",
+    Center = 0x03 => "|",
+    R      = 0x04 => ">",
+    RR     = 0x05 => ">>",
+    LR     = 0x08 => "<>",
+    Swing  = 0x0c => "SWING",
+});
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ISee {
+    Off = 0x00,
+    On  = 0x01,
 }
 
-impl OneByteEncodable for Mode {
-    fn encoded_as_byte(&self) -> u8 {
-        self.repr()
+impl TryFrom<u8> for ISee {
+    type Error = UnknownValue;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0x00 => Ok(ISee::Off),
+            0x01 => Ok(ISee::On),
+            _ => Err(UnknownValue { type_name: "ISee", byte }),
+        }
     }
 }
 
-#[EnumRepr(type="u8")]
-#[derive(Debug, Eq, PartialEq)]
-pub enum Fan {
-    Auto  = 0x00,
-    Quiet = 0x01,
-    F1    = 0x02,
-    F2    = 0x03,
-    F3    = 0x05,
-    F4    = 0x06,
+/// A temperature as encoded by a unit. The checked constructors reject
+/// bytes no unit would send; the variants can still be built directly, e.g.
+/// to pass a captured byte through unmodified, as with the settings'
+/// `Other` values.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Temperature {
+    HalfDegreesCPlusOffset { value: u8 },
+    SetpointMapped { value: u8 },
+    RoomTempMapped { value: u8 },
+}
+
+/// An encoded temperature a unit can't send, along with the lowest and
+/// highest bytes it can in that encoding
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ImpossibleTemperature {
+    pub temperature: Temperature,
+    pub min: u8,
+    pub max: u8,
 }
 
-impl OneByteEncodable for Fan {
-    fn encoded_as_byte(&self) -> u8 {
-        self.repr()
+impl Temperature {
+    /// 0°C and above
+    const HALF_DEGREES_RANGE: (u8, u8) = (0x80, 0xff);
+    /// 31°C down to 10°C
+    const SETPOINT_MAPPED_RANGE: (u8, u8) = (0x00, 0x15);
+    /// 10°C up to 41°C
+    const ROOM_TEMP_MAPPED_RANGE: (u8, u8) = (0x00, 0x1f);
+
+    pub fn half_degrees(value: u8) -> Result<Self, ImpossibleTemperature> {
+        Temperature::HalfDegreesCPlusOffset { value }.checked()
     }
-}
 
-#[EnumRepr(type="u8")]
-#[derive(Debug, Eq, PartialEq)]
-pub enum Vane {
-    Auto  = 0x00,
-    V1    = 0x01,
-    V2    = 0x02,
-    V3    = 0x03,
-    V4    = 0x04,
-    V5    = 0x05,
-    Swing = 0x07,
-}
+    pub fn setpoint_mapped(value: u8) -> Result<Self, ImpossibleTemperature> {
+        Temperature::SetpointMapped { value }.checked()
+    }
+
+    pub fn room_temp_mapped(value: u8) -> Result<Self, ImpossibleTemperature> {
+        Temperature::RoomTempMapped { value }.checked()
+    }
+
+    /// The lowest and highest bytes a unit sends in this encoding: 0°C and
+    /// above in half degrees, 10°C to 31°C for mapped setpoints, and 10°C to
+    /// 41°C for mapped room temperatures
+    pub fn valid_range(&self) -> (u8, u8) {
+        match *self {
+            Temperature::HalfDegreesCPlusOffset { .. } => Self::HALF_DEGREES_RANGE,
+            Temperature::SetpointMapped { .. } => Self::SETPOINT_MAPPED_RANGE,
+            Temperature::RoomTempMapped { .. } => Self::ROOM_TEMP_MAPPED_RANGE,
+        }
+    }
 
-impl OneByteEncodable for Vane {
-    fn encoded_as_byte(&self) -> u8 {
-        self.repr()
+    /// The temperature, if it's one a unit can send
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::types::{ImpossibleTemperature, Temperature};
+    ///
+    /// let below_zero = Temperature::HalfDegreesCPlusOffset { value: 0x7f };
+    /// assert_eq!(
+    ///     Err(ImpossibleTemperature { temperature: below_zero, min: 0x80, max: 0xff }),
+    ///     below_zero.checked()
+    /// );
+    /// ```
+    pub fn checked(self) -> Result<Self, ImpossibleTemperature> {
+        let (min, max) = self.valid_range();
+        let value = match self {
+            Temperature::HalfDegreesCPlusOffset { value }
+            | Temperature::SetpointMapped { value }
+            | Temperature::RoomTempMapped { value } => value,
+        };
+        if (min..=max).contains(&value) {
+            Ok(self)
+        } else {
+            Err(ImpossibleTemperature { temperature: self, min, max })
+        }
+    }
+
+    /// Converts to tenths of a degree C. Never panics, even for byte values
+    /// the device wouldn't send.
+    pub fn celsius_tenths(&self) -> TenthDegreesC {
+        match *self {
+            Temperature::HalfDegreesCPlusOffset { value } => TenthDegreesC((value as i16 - 128) * 5),
+            Temperature::SetpointMapped { value } => TenthDegreesC((0x1f - value as i16) * 10),
+            Temperature::RoomTempMapped { value } => TenthDegreesC((value as i16 + 10) * 10),
+        }
     }
 }
 
-#[EnumRepr(type="u8")]
-#[derive(Debug, Eq, PartialEq)]
-pub enum WideVane {
-    LL     = 0x01,
-    L      = 0x02,
-    Center = 0x03,
-    R      = 0x04,
-    RR     = 0x05,
-    LR     = 0x08,
-    Swing  = 0x0c,
+/// How to round a temperature to the unit's half-degree steps
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Rounding {
+    /// Down, so a heating setpoint never ends up warmer than asked for
+    Floor,
+    /// Up, so a cooling setpoint never ends up cooler than asked for
+    Ceil,
+    #[default]
+    Nearest,
 }
 
-impl OneByteEncodable for WideVane {
-    fn encoded_as_byte(&self) -> u8 {
-        self.repr()
+/// A temperature in tenths of a degree C. When encoded, temperatures outside
+/// of the range units send in an encoding are clamped to it, and in half degrees are rounded
+/// to the nearest half degree.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct TenthDegreesC(pub i16);
+
+impl TenthDegreesC {
+    pub fn encode_as_setpoint_mapped(&self) -> u8 {
+        clamp_to(0x1f - self.0 / 10, Temperature::SETPOINT_MAPPED_RANGE)
     }
-}
 
-#[EnumRepr(type="u8")]
-#[derive(Debug, Eq, PartialEq)]
-pub enum ISee {
-    Off = 0x00,
-    On  = 0x01,
+    pub fn encode_as_room_temp_mapped(&self) -> u8 {
+        clamp_to(self.0 / 10 - 10, Temperature::ROOM_TEMP_MAPPED_RANGE)
+    }
+
+    pub fn encode_as_half_deg_plus_offset(&self) -> u8 {
+        clamp_to(self.round_to_half_degree(Rounding::Nearest).0 / 5 + 128, Temperature::HALF_DEGREES_RANGE)
+    }
+
+    /// Rounds to a whole or half degree, saturating at the limits of `i16`
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::types::{Rounding, TenthDegreesC};
+    ///
+    /// let t = TenthDegreesC(213);
+    /// assert_eq!(TenthDegreesC(210), t.round_to_half_degree(Rounding::Floor));
+    /// assert_eq!(TenthDegreesC(215), t.round_to_half_degree(Rounding::Ceil));
+    /// assert_eq!(TenthDegreesC(215), t.round_to_half_degree(Rounding::Nearest));
+    /// ```
+    pub fn round_to_half_degree(&self, rounding: Rounding) -> Self {
+        let tenths = i32::from(self.0);
+        let floor = tenths.div_euclid(5) * 5;
+        let rounded = match rounding {
+            Rounding::Floor => floor,
+            Rounding::Ceil if floor == tenths => floor,
+            Rounding::Ceil => floor + 5,
+            Rounding::Nearest if tenths - floor >= 3 => floor + 5,
+            Rounding::Nearest => floor,
+        };
+        TenthDegreesC(rounded.clamp(i16::MIN.into(), i16::MAX.into()) as i16)
+    }
+
+    /// Converts from thousandths of a degree C, as many temperature sensor
+    /// drivers report, rounding to the nearest tenth and saturating at the
+    /// limits of `i16`
+    ///
+    /// ```
+    /// use mitsu_ac::protocol::types::TenthDegreesC;
+    ///
+    /// assert_eq!(TenthDegreesC(215), TenthDegreesC::from_millidegrees(21_450));
+    /// assert_eq!(TenthDegreesC(-215), TenthDegreesC::from_millidegrees(-21_450));
+    /// assert_eq!(21_500, TenthDegreesC(215).as_millidegrees());
+    /// ```
+    pub fn from_millidegrees(millidegrees: i32) -> Self {
+        let half = if millidegrees < 0 { -50 } else { 50 };
+        let tenths = (millidegrees.saturating_add(half) / 100).clamp(i16::MIN.into(), i16::MAX.into());
+        TenthDegreesC(tenths as i16)
+    }
+
+    pub fn as_millidegrees(&self) -> i32 {
+        i32::from(self.0) * 100
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Temperature {
-    HalfDegreesCPlusOffset { value: u8 },
-    SetpointMapped { value: u8 },
-    RoomTempMapped { value: u8 },
+/// A setpoint, as distinct from a temperature reading. Setpoints built by hand
+/// are checked against the range the unit accepts; those reported by the unit
+/// are taken as they are.
+///
+/// ```
+/// use mitsu_ac::protocol::types::{Mode, Setpoint, TenthDegreesC};
+///
+/// let setpoint = Setpoint::new(TenthDegreesC(120)).unwrap();
+/// assert!(setpoint.is_valid_for(Mode::Heat));
+/// assert!(!setpoint.is_valid_for(Mode::Cool));
+/// assert!(Setpoint::for_mode(TenthDegreesC(120), Mode::Cool).is_err());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Setpoint(pub(crate) TenthDegreesC);
+
+/// A setpoint outside of the range allowed, which is included
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SetpointOutOfRange {
+    pub min: TenthDegreesC,
+    pub max: TenthDegreesC,
 }
 
-impl Temperature {
+impl Setpoint {
+    /// The lowest setpoint in any mode, 10°C, only allowed when heating
+    pub const MIN: TenthDegreesC = TenthDegreesC(100);
+    /// The lowest setpoint outside of heat mode, 16°C
+    pub const MIN_COOLING: TenthDegreesC = TenthDegreesC(160);
+    /// The highest setpoint in any mode, 31°C
+    pub const MAX: TenthDegreesC = TenthDegreesC(310);
+
+    /// A setpoint within the range allowed by any mode
+    pub fn new(temperature: TenthDegreesC) -> Result<Self, SetpointOutOfRange> {
+        Self::checked(temperature, Self::MIN, Self::MAX)
+    }
+
+    /// A setpoint within the range allowed in `mode`
+    pub fn for_mode(temperature: TenthDegreesC, mode: Mode) -> Result<Self, SetpointOutOfRange> {
+        let (min, max) = Self::range(mode);
+        Self::checked(temperature, min, max)
+    }
+
+    /// The lowest and highest setpoints allowed in `mode`
+    pub fn range(mode: Mode) -> (TenthDegreesC, TenthDegreesC) {
+        match mode {
+            Mode::Heat => (Self::MIN, Self::MAX),
+            _ => (Self::MIN_COOLING, Self::MAX),
+        }
+    }
+
+    pub fn is_valid_for(&self, mode: Mode) -> bool {
+        let (min, max) = Self::range(mode);
+        (min..=max).contains(&self.0)
+    }
+
     pub fn celsius_tenths(&self) -> TenthDegreesC {
-        match self {
-            Temperature::HalfDegreesCPlusOffset { value } => TenthDegreesC((value - 128) * 5),
-            Temperature::SetpointMapped { value } => TenthDegreesC((0x1f - value) * 10),
-            Temperature::RoomTempMapped { value } => TenthDegreesC((value + 10) * 10),
+        self.0
+    }
+
+    /// The setpoint rounded to the half-degree steps the unit works in. The
+    /// ends of each mode's range are whole degrees, so a valid setpoint stays
+    /// valid.
+    pub fn rounded(&self, rounding: Rounding) -> Self {
+        Setpoint(self.0.round_to_half_degree(rounding))
+    }
+
+    fn checked(temperature: TenthDegreesC, min: TenthDegreesC, max: TenthDegreesC) -> Result<Self, SetpointOutOfRange> {
+        if (min..=max).contains(&temperature) {
+            Ok(Setpoint(temperature))
+        } else {
+            Err(SetpointOutOfRange { min, max })
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct TenthDegreesC(pub u8);
+impl From<Setpoint> for TenthDegreesC {
+    fn from(setpoint: Setpoint) -> Self {
+        setpoint.0
+    }
+}
 
-impl TenthDegreesC {
-    pub fn encode_as_setpoint_mapped(&self) -> u8 { 0x1f - self.0 / 10 }
-    pub fn encode_as_room_temp_mapped(&self) -> u8 { self.0 / 10 - 10 }
-    pub fn encode_as_half_deg_plus_offset(&self) -> u8 { self.0 / 5 + 128 }
+fn clamp_to(value: i16, (min, max): (u8, u8)) -> u8 {
+    value.clamp(min.into(), max.into()) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_round_trips_test() {
+        assert_eq!(Fan::F3, Fan::from_byte(0x05));
+        assert_eq!(Fan::Other(0x04), Fan::from_byte(0x04));
+        assert_eq!(0x04, Fan::Other(0x04).encoded_as_byte());
+        assert_eq!(0x0c, WideVane::from_byte(0x0c).encoded_as_byte());
+    }
+
+    #[test]
+    fn try_from_test() {
+        assert_eq!(Ok(Mode::Cool), Mode::try_from(0x03));
+        assert_eq!(Err(UnknownValue { type_name: "Mode", byte: 0x04 }), Mode::try_from(0x04));
+        assert_eq!(Err(UnknownValue { type_name: "ISee", byte: 0x02 }), ISee::try_from(0x02));
+    }
+
+    #[test]
+    fn names_test() {
+        assert_eq!("QUIET", Fan::Quiet.as_str());
+        assert_eq!("<>", WideVane::LR.as_str());
+        assert_eq!("UNKNOWN", Vane::Other(0x06).as_str());
+        assert_eq!(Ok(Mode::Heat), "heat".parse());
+        assert_eq!(Ok(Vane::V3), "3".parse());
+        assert_eq!(Err(UnknownName { type_name: "Power" }), "UNKNOWN".parse::<Power>());
+    }
+
+    #[test]
+    fn checked_test() {
+        assert!(Temperature::HalfDegreesCPlusOffset { value: 0x80 }.checked().is_ok());
+        assert!(Temperature::SetpointMapped { value: 0x15 }.checked().is_ok());
+        assert!(Temperature::SetpointMapped { value: 0x16 }.checked().is_err());
+        assert!(Temperature::RoomTempMapped { value: 0x1f }.checked().is_ok());
+        assert!(Temperature::RoomTempMapped { value: 0x20 }.checked().is_err());
+    }
+
+    #[test]
+    fn celsius_tenths_test() {
+        assert_eq!(TenthDegreesC(210), Temperature::HalfDegreesCPlusOffset { value: 0xaa }.celsius_tenths());
+        assert_eq!(TenthDegreesC(-640), Temperature::HalfDegreesCPlusOffset { value: 0x00 }.celsius_tenths());
+        assert_eq!(TenthDegreesC(635), Temperature::HalfDegreesCPlusOffset { value: 0xff }.celsius_tenths());
+        assert_eq!(TenthDegreesC(-2240), Temperature::SetpointMapped { value: 0xff }.celsius_tenths());
+        assert_eq!(TenthDegreesC(2650), Temperature::RoomTempMapped { value: 0xff }.celsius_tenths());
+    }
+
+    #[test]
+    fn round_to_half_degree_test() {
+        let round = |tenths, rounding| TenthDegreesC(tenths).round_to_half_degree(rounding).0;
+        assert_eq!(-215, round(-213, Rounding::Floor));
+        assert_eq!(-210, round(-213, Rounding::Ceil));
+        assert_eq!(-215, round(-213, Rounding::Nearest));
+        assert_eq!(210, round(212, Rounding::Nearest));
+        assert_eq!(210, round(210, Rounding::Ceil));
+        assert_eq!(i16::MAX, round(i16::MAX, Rounding::Ceil));
+        assert_eq!(i16::MIN, round(i16::MIN, Rounding::Floor));
+
+        let setpoint = Setpoint::for_mode(TenthDegreesC(309), Mode::Cool).unwrap();
+        assert!(setpoint.rounded(Rounding::Ceil).is_valid_for(Mode::Cool));
+    }
+
+    #[test]
+    fn millidegrees_test() {
+        assert_eq!(TenthDegreesC(0), TenthDegreesC::from_millidegrees(49));
+        assert_eq!(TenthDegreesC(1), TenthDegreesC::from_millidegrees(50));
+        assert_eq!(TenthDegreesC(-1), TenthDegreesC::from_millidegrees(-50));
+        assert_eq!(TenthDegreesC(i16::MAX), TenthDegreesC::from_millidegrees(i32::MAX));
+        assert_eq!(TenthDegreesC(i16::MIN), TenthDegreesC::from_millidegrees(i32::MIN));
+        assert_eq!(-3_276_800, TenthDegreesC(i16::MIN).as_millidegrees());
+    }
+
+    #[test]
+    fn setpoint_test() {
+        assert_eq!(Ok(Setpoint(TenthDegreesC(215))), Setpoint::new(TenthDegreesC(215)));
+        assert_eq!(
+            Err(SetpointOutOfRange { min: TenthDegreesC(100), max: TenthDegreesC(310) }),
+            Setpoint::new(TenthDegreesC(315))
+        );
+        assert!(Setpoint::for_mode(TenthDegreesC(100), Mode::Heat).is_ok());
+        assert_eq!(
+            Err(SetpointOutOfRange { min: TenthDegreesC(160), max: TenthDegreesC(310) }),
+            Setpoint::for_mode(TenthDegreesC(100), Mode::Auto)
+        );
+    }
+
+    #[test]
+    fn encode_clamps_test() {
+        assert_eq!(0xbe, TenthDegreesC(310).encode_as_half_deg_plus_offset());
+        assert_eq!(0xff, TenthDegreesC(i16::MAX).encode_as_half_deg_plus_offset());
+        assert_eq!(0xab, TenthDegreesC(213).encode_as_half_deg_plus_offset());
+        assert_eq!(0x00, TenthDegreesC(i16::MAX).encode_as_setpoint_mapped());
+        assert_eq!(0x00, TenthDegreesC(i16::MIN).encode_as_room_temp_mapped());
+        assert_eq!(0x1f, TenthDegreesC(i16::MAX).encode_as_room_temp_mapped());
+        assert_eq!(0x15, TenthDegreesC(0).encode_as_setpoint_mapped());
+        assert_eq!(0x80, TenthDegreesC(-35).encode_as_half_deg_plus_offset());
+        assert!(Temperature::half_degrees(TenthDegreesC(-35).encode_as_half_deg_plus_offset()).is_ok());
+    }
 }
@@ -0,0 +1,98 @@
+use super::frame::{Frame, DataType, Header};
+use super::frame_data::{ConnectRequest, InfoType};
+use super::reader::FrameReader;
+use crate::climate::ClimateTraits;
+
+/// The quirks of a family of units speaking a variant of the CN105 protocol.
+/// Every item has a default matching the units this crate was written
+/// against, so a variant only overrides what differs.
+///
+/// ```
+/// use mitsu_ac::protocol::{ProtocolVariant, Header, InfoType};
+///
+/// /// A unit that doesn't answer `Type4` requests
+/// struct NoType4;
+///
+/// impl ProtocolVariant for NoType4 {
+///     const INFO_TYPES: &'static [InfoType] =
+///         &[InfoType::Settings, InfoType::RoomTemp, InfoType::Status];
+/// }
+///
+/// assert_eq!(Header::CN105, NoType4::HEADER);
+/// assert!(!NoType4::supports(InfoType::Type4));
+/// ```
+pub trait ProtocolVariant {
+    /// The header bytes of every frame
+    const HEADER: Header = Header::CN105;
+
+    /// The data sent in a `ConnectRequest`
+    const CONNECT_PAYLOAD: &'static [u8] = &[ConnectRequest::BYTE1, ConnectRequest::BYTE2];
+
+    /// The `InfoType`s the unit answers, in the order they should be polled
+    const INFO_TYPES: &'static [InfoType] = &InfoType::POLLED;
+
+    /// The modes, fan speeds and setpoints the unit accepts
+    const CLIMATE_TRAITS: ClimateTraits = ClimateTraits::DEFAULT;
+
+    /// Whether the unit answers requests for `info_type`
+    fn supports(info_type: InfoType) -> bool {
+        Self::INFO_TYPES.contains(&info_type)
+    }
+
+    /// The frame to send to connect to the unit
+    fn connect_frame() -> Frame<&'static [u8]> {
+        Frame::new(DataType::ConnectRequest, Self::CONNECT_PAYLOAD).with_header(Self::HEADER)
+    }
+
+    /// A `FrameReader` expecting this variant's header bytes
+    fn frame_reader() -> FrameReader {
+        FrameReader::with_header(Self::HEADER)
+    }
+}
+
+/// The wall-mounted units (MSZ, MUZ and similar) this crate was written
+/// against
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Cn105;
+
+impl ProtocolVariant for Cn105 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::encoding::Encodable;
+    use super::super::types::Setpoint;
+
+    struct Other;
+
+    impl ProtocolVariant for Other {
+        const HEADER: Header = Header::new(0x02, 0x30);
+        const CONNECT_PAYLOAD: &'static [u8] = &[0xca, 0x02];
+    }
+
+    #[test]
+    fn connect_frame_test() {
+        let mut buf = [0u8; 8];
+        assert_eq!(Ok(8), Cn105::connect_frame().encode(&mut buf));
+        assert_eq!([0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], buf);
+
+        assert_eq!(Ok(8), Other::connect_frame().encode(&mut buf));
+        assert_eq!([0xfc, 0x5a, 0x02, 0x30, 0x02, 0xca, 0x02, 0xa6], buf);
+    }
+
+    #[test]
+    fn climate_traits_test() {
+        let traits = Cn105::CLIMATE_TRAITS;
+        for &mode in traits.modes {
+            let (min, max) = traits.range(mode);
+            assert_eq!(Setpoint::range(mode), (min, max));
+            assert_eq!(0, (max.0 - min.0) % traits.step.0);
+        }
+    }
+
+    #[test]
+    fn supports_test() {
+        assert!(InfoType::POLLED.iter().all(|&t| Cn105::supports(t)));
+        assert!(!Cn105::supports(InfoType::Timers));
+    }
+}
@@ -0,0 +1,93 @@
+//! Python bindings
+//!
+//! Exposes the frame parser and payload decoders to Python, so logs captured
+//! from a heat pump can be analysed with exactly the same decoder the
+//! firmware uses.
+//!
+//! Build the extension module with the `python` feature and a `cdylib` crate
+//! type, for example:
+//!
+//! ```sh
+//! cargo rustc --release --features python,pyo3/extension-module --crate-type cdylib
+//! ```
+//!
+//! and copy the resulting library to `mitsu_ac.so` somewhere on Python's path.
+//!
+//! ```python
+//! import mitsu_ac
+//!
+//! data_type, payload, consumed = mitsu_ac.parse_frame(capture)
+//! if data_type == 0x62:
+//!     print(mitsu_ac.decode_get_info_response(payload))
+//! ```
+
+// `#[pyfunction]` expands to a `PyErr` -> `PyErr` conversion
+#![allow(clippy::useless_conversion)]
+
+use std::format;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+use crate::protocol::{Frame, FrameData, DataType, GetInfoResponse, Settings};
+
+/// Parses the first frame found in `data`, skipping any leading junk.
+///
+/// Returns a `(data_type, payload, consumed)` tuple, where `consumed` is the
+/// number of bytes of `data` used up, including junk.
+#[pyfunction]
+fn parse_frame<'py>(py: Python<'py>, data: &[u8]) -> PyResult<(u8, Bound<'py, PyBytes>, usize)> {
+    let (rest, _) = Frame::parse_until(data).map_err(value_error)?;
+    let (rest, frame) = Frame::parse(rest).map_err(value_error)?;
+    Ok((frame.data_type.as_byte(), PyBytes::new_bound(py, frame.data), data.len() - rest.len()))
+}
+
+/// Decodes a `GetInfoResponse` payload into a dict. Unknown info types decode
+/// to `{"info_type": "Unknown"}`.
+#[pyfunction]
+fn decode_get_info_response<'py>(py: Python<'py>, payload: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let frame = Frame::new(DataType::GetInfoResponse, payload);
+    let (_, data) = FrameData::parse(frame).map_err(value_error)?;
+
+    let dict = PyDict::new_bound(py);
+    match data {
+        FrameData::GetInfoResponse(GetInfoResponse::Settings(Settings { power, mode, setpoint, fan, vane, widevane, isee })) => {
+            dict.set_item("info_type", "Settings")?;
+            dict.set_item("power", format!("{:?}", power))?;
+            dict.set_item("mode", format!("{:?}", mode))?;
+            dict.set_item("setpoint_tenths", setpoint.celsius_tenths().0)?;
+            dict.set_item("fan", format!("{:?}", fan))?;
+            dict.set_item("vane", format!("{:?}", vane))?;
+            dict.set_item("widevane", format!("{:?}", widevane))?;
+            dict.set_item("isee", format!("{:?}", isee))?;
+        },
+        FrameData::GetInfoResponse(GetInfoResponse::RoomTemperature { temperature }) => {
+            dict.set_item("info_type", "RoomTemperature")?;
+            dict.set_item("temperature_tenths", temperature.celsius_tenths().0)?;
+        },
+        FrameData::GetInfoResponse(GetInfoResponse::Faults { error_code, fault }) => {
+            dict.set_item("info_type", "Faults")?;
+            dict.set_item("error_code", error_code)?;
+            dict.set_item("fault", fault.as_ref().map(|f| f.as_str()))?;
+        },
+        FrameData::GetInfoResponse(GetInfoResponse::Status { compressor_frequency, operating }) => {
+            dict.set_item("info_type", "Status")?;
+            dict.set_item("compressor_frequency", compressor_frequency)?;
+            dict.set_item("operating", operating)?;
+        },
+        _ => dict.set_item("info_type", "Unknown")?,
+    }
+    Ok(dict)
+}
+
+fn value_error<E: core::fmt::Debug>(e: E) -> PyErr {
+    PyValueError::new_err(format!("{:?}", e))
+}
+
+#[pymodule]
+fn mitsu_ac(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_frame, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_get_info_response, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! A small pseudo-random number generator for fault injection and fuzzing
+//! tests, where the same seed has to give the same sequence every time
+
+/// xorshift64. Not remotely cryptographic, just cheap and repeatable.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) struct XorShift64 {
+    state: u64,
+}
+
+impl XorShift64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero
+        let state = if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed };
+        Self { state }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
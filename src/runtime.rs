@@ -0,0 +1,159 @@
+//! Compressor runtime accounting
+//!
+//! Accumulates how long the compressor has run, how often it has started,
+//! and what fraction of each window of time it was running for, from
+//! periodic `Status` polls. For energy dashboards, and for scheduling filter
+//! cleaning and other maintenance by hours run. Times are plain tick counts
+//! in whatever unit the caller uses, as in `trace`.
+//!
+//! ```
+//! use mitsu_ac::protocol::GetInfoResponse;
+//! use mitsu_ac::runtime::Runtime;
+//!
+//! let mut runtime = Runtime::new(100);
+//!
+//! runtime.record_status(&GetInfoResponse::Status { compressor_frequency: 0, operating: 0 }, 0);
+//! runtime.record_status(&GetInfoResponse::Status { compressor_frequency: 42, operating: 1 }, 75);
+//! runtime.record_status(&GetInfoResponse::Status { compressor_frequency: 40, operating: 1 }, 100);
+//!
+//! assert_eq!(25, runtime.runtime());
+//! assert_eq!(1, runtime.cycles());
+//! assert_eq!(Some(25), runtime.duty_cycle());
+//! ```
+
+use crate::protocol::GetInfoResponse;
+
+/// Runtime, start count and duty cycle of a compressor, accumulated from
+/// samples of whether it's running. The time between two samples is
+/// counted as running if the first of them was.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Runtime {
+    window: u32,
+    last: Option<(u32, bool)>,
+    runtime: u64,
+    cycles: u32,
+    window_start: u32,
+    window_running: u32,
+    duty_cycle: Option<u8>,
+}
+
+impl Runtime {
+    /// Accounting with duty cycles measured over `window` ticks, e.g. an hour
+    pub fn new(window: u32) -> Self {
+        Self {
+            window,
+            last: None,
+            runtime: 0,
+            cycles: 0,
+            window_start: 0,
+            window_running: 0,
+            duty_cycle: None,
+        }
+    }
+
+    /// Records whether the compressor is running at tick `now`
+    pub fn record(&mut self, running: bool, now: u32) {
+        match self.last {
+            None => self.window_start = now,
+            Some((then, was_running)) => {
+                if was_running {
+                    let elapsed = now.wrapping_sub(then);
+                    self.runtime += u64::from(elapsed);
+                    self.window_running = self.window_running.saturating_add(elapsed);
+                }
+                if running && !was_running {
+                    self.cycles = self.cycles.wrapping_add(1);
+                }
+            },
+        }
+        self.last = Some((now, running));
+
+        let elapsed = now.wrapping_sub(self.window_start);
+        if elapsed >= self.window && elapsed > 0 {
+            self.duty_cycle = Some(percent(self.window_running, elapsed));
+            self.window_start = now;
+            self.window_running = 0;
+        }
+    }
+
+    /// Records a `Status` response received at tick `now`. The compressor is
+    /// taken to be running if either of its fields is non-zero. Other
+    /// responses are ignored.
+    pub fn record_status(&mut self, response: &GetInfoResponse, now: u32) {
+        if let GetInfoResponse::Status { compressor_frequency, operating } = response {
+            self.record(*compressor_frequency != 0 || *operating != 0, now);
+        }
+    }
+
+    /// The total ticks the compressor has run for
+    pub fn runtime(&self) -> u64 {
+        self.runtime
+    }
+
+    /// The number of times the compressor has started. Counted when it's
+    /// first seen running after being seen stopped, so a compressor already
+    /// running at the first sample isn't counted.
+    pub fn cycles(&self) -> u32 {
+        self.cycles
+    }
+
+    /// The percentage of the last complete window the compressor ran for.
+    /// A window closes at the first sample at least `window` ticks after it
+    /// opened, so may run a little long.
+    pub fn duty_cycle(&self) -> Option<u8> {
+        self.duty_cycle
+    }
+
+    /// The percentage of the window so far the compressor ran for, if any
+    /// time has passed in it
+    pub fn current_duty_cycle(&self) -> Option<u8> {
+        let (now, _) = self.last?;
+        match now.wrapping_sub(self.window_start) {
+            0 => None,
+            elapsed => Some(percent(self.window_running, elapsed)),
+        }
+    }
+
+    /// Starts counting again from zero, e.g. after maintenance
+    pub fn reset(&mut self) {
+        *self = Self::new(self.window);
+    }
+}
+
+fn percent(part: u32, whole: u32) -> u8 {
+    (u64::from(part) * 100 / u64::from(whole)).min(100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_test() {
+        let mut runtime = Runtime::new(10);
+        runtime.record(true, 0);
+        assert_eq!(None, runtime.current_duty_cycle());
+        runtime.record(false, 5);
+        assert_eq!(Some(100), runtime.current_duty_cycle());
+        runtime.record(true, 10);
+        assert_eq!(Some(50), runtime.duty_cycle());
+        assert_eq!(None, runtime.current_duty_cycle());
+
+        runtime.record(true, 22);
+        assert_eq!(Some(100), runtime.duty_cycle());
+        assert_eq!(17, runtime.runtime());
+        assert_eq!(1, runtime.cycles());
+
+        runtime.reset();
+        assert_eq!((0, 0, None), (runtime.runtime(), runtime.cycles(), runtime.duty_cycle()));
+    }
+
+    #[test]
+    fn tick_wraparound_test() {
+        let mut runtime = Runtime::new(10);
+        runtime.record(true, u32::MAX - 4);
+        runtime.record(false, 5);
+        assert_eq!(10, runtime.runtime());
+        assert_eq!(Some(100), runtime.duty_cycle());
+    }
+}
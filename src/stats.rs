@@ -0,0 +1,356 @@
+//! Link statistics
+//!
+//! Counters for monitoring the health of a long-running link. Whatever owns
+//! the serial line records events as they happen; the counters wrap rather
+//! than overflow. `LinkQuality` turns the checksum failure rate into a simple
+//! Good/Marginal/Bad classification, and `RoundTripTimes` measures how long
+//! the unit takes to respond.
+//!
+//! ```
+//! use mitsu_ac::protocol::Frame;
+//! use mitsu_ac::stats::Stats;
+//!
+//! let mut stats = Stats::new();
+//!
+//! let result = Frame::parse(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x55]);
+//! stats.record_parse(&result);
+//!
+//! assert_eq!(stats.checksum_failures, 1);
+//! ```
+
+use crate::protocol::{DecodingError, ScanStats};
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Stats {
+    /// Frames successfully parsed
+    pub frames_parsed: u32,
+    /// Frames transmitted
+    pub frames_sent: u32,
+    /// Frames rejected due to an invalid checksum
+    pub checksum_failures: u32,
+    /// Times junk was skipped to find the start of the next frame
+    pub resyncs: u32,
+    /// Requests re-sent after a failure or timeout
+    pub retries: u32,
+    /// Requests which never received a response
+    pub timeouts: u32,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the outcome of parsing a frame. Incomplete input isn't counted,
+    /// as parsing will be retried once more bytes arrive.
+    pub fn record_parse<T>(&mut self, result: &Result<T, DecodingError>) {
+        match result {
+            Ok(_) => increment(&mut self.frames_parsed),
+            Err(DecodingError::InvalidChecksum { .. }) => increment(&mut self.checksum_failures),
+            Err(DecodingError::BadHeader) => increment(&mut self.resyncs),
+            Err(_) => {},
+        }
+    }
+
+    /// Records the outcome of `Frame::scan_with_stats`, including the bad
+    /// frames it skipped past
+    pub fn record_scan<T>(&mut self, result: &Result<T, DecodingError>, scan: &ScanStats) {
+        self.record_parse(result);
+        self.resyncs = self.resyncs.wrapping_add(scan.resyncs);
+        self.checksum_failures = self.checksum_failures.wrapping_add(scan.checksum_failures);
+    }
+
+    pub fn record_sent(&mut self) {
+        increment(&mut self.frames_sent);
+    }
+
+    pub fn record_resync(&mut self) {
+        increment(&mut self.resyncs);
+    }
+
+    pub fn record_retry(&mut self) {
+        increment(&mut self.retries);
+    }
+
+    pub fn record_timeout(&mut self) {
+        increment(&mut self.timeouts);
+    }
+
+    /// Zeroes all of the counters
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LinkHealth {
+    Good,
+    Marginal,
+    Bad,
+}
+
+/// Classifies link health from the proportion of frames failing their
+/// checksum, measured over consecutive windows of received frames. The
+/// classification is updated at the end of each window.
+///
+/// ```
+/// use mitsu_ac::stats::{LinkQuality, LinkHealth};
+///
+/// // Windows of 10 frames; marginal at 10% failures, bad at 30%
+/// let mut quality = LinkQuality::new(10, 10, 30);
+///
+/// for i in 0..10 {
+///     if let Some(health) = quality.record_frame(i % 3 != 0) {
+///         assert_eq!(health, LinkHealth::Bad);
+///         println!("Link is now {:?}, check the wiring", health);
+///     }
+/// }
+/// assert_eq!(quality.health(), LinkHealth::Bad);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LinkQuality {
+    window: u32,
+    marginal_percent: u32,
+    bad_percent: u32,
+    frames: u32,
+    failures: u32,
+    health: LinkHealth,
+}
+
+impl LinkQuality {
+    /// Classifies every `window` frames: at least `marginal_percent` failing
+    /// is `Marginal`, at least `bad_percent` is `Bad`.
+    pub fn new(window: u32, marginal_percent: u32, bad_percent: u32) -> Self {
+        Self {
+            window: window.max(1),
+            marginal_percent,
+            bad_percent,
+            frames: 0,
+            failures: 0,
+            health: LinkHealth::Good,
+        }
+    }
+
+    /// The classification as of the end of the last complete window
+    pub fn health(&self) -> LinkHealth {
+        self.health
+    }
+
+    /// Records a received frame. Returns the new classification if it changed
+    /// at the end of a window.
+    pub fn record_frame(&mut self, checksum_ok: bool) -> Option<LinkHealth> {
+        self.frames += 1;
+        if !checksum_ok { self.failures += 1; }
+        if self.frames < self.window { return None; }
+
+        let percent = self.failures * 100 / self.frames;
+        let health = if percent >= self.bad_percent {
+            LinkHealth::Bad
+        } else if percent >= self.marginal_percent {
+            LinkHealth::Marginal
+        } else {
+            LinkHealth::Good
+        };
+
+        self.frames = 0;
+        self.failures = 0;
+
+        if health == self.health {
+            None
+        } else {
+            self.health = health;
+            Some(health)
+        }
+    }
+
+    /// Records the outcome of parsing a frame. Only successes and checksum
+    /// failures are counted.
+    pub fn record_parse<T>(&mut self, result: &Result<T, DecodingError>) -> Option<LinkHealth> {
+        match result {
+            Ok(_) => self.record_frame(true),
+            Err(DecodingError::InvalidChecksum { .. }) => self.record_frame(false),
+            Err(_) => None,
+        }
+    }
+}
+
+/// Windows of 50 frames, marginal at 2% checksum failures and bad at 10%
+impl Default for LinkQuality {
+    fn default() -> Self {
+        Self::new(50, 2, 10)
+    }
+}
+
+/// Round-trip times between each request sent and its response, in
+/// whatever ticks the caller uses. CN105 has one request outstanding at a
+/// time, so a response always matches the last request sent.
+///
+/// ```
+/// use mitsu_ac::stats::RoundTripTimes;
+///
+/// let mut rtt = RoundTripTimes::new();
+/// rtt.record_sent(100);
+/// assert_eq!(Some(45), rtt.record_response(145));
+/// rtt.record_sent(200);
+/// rtt.record_response(255);
+///
+/// assert_eq!(Some(45), rtt.min());
+/// assert_eq!(Some(50), rtt.average());
+/// assert_eq!(Some(55), rtt.max());
+/// ```
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct RoundTripTimes {
+    outstanding: Option<u32>,
+    count: u32,
+    total: u64,
+    min: u32,
+    max: u32,
+}
+
+impl RoundTripTimes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a request sent at tick `now`
+    pub fn record_sent(&mut self, now: u32) {
+        self.outstanding = Some(now);
+    }
+
+    /// Records a response received at tick `now`, returning its round-trip
+    /// time. Responses with no request outstanding are ignored.
+    pub fn record_response(&mut self, now: u32) -> Option<u32> {
+        let sent = self.outstanding.take()?;
+        let rtt = now.wrapping_sub(sent);
+
+        if self.count == 0 {
+            self.min = rtt;
+            self.max = rtt;
+        } else {
+            self.min = self.min.min(rtt);
+            self.max = self.max.max(rtt);
+        }
+        self.count = self.count.saturating_add(1);
+        self.total = self.total.saturating_add(rtt as u64);
+        Some(rtt)
+    }
+
+    /// Forgets the outstanding request, which will never get a response
+    pub fn record_timeout(&mut self) {
+        self.outstanding = None;
+    }
+
+    /// The number of round trips measured
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<u32> {
+        if self.count == 0 { None } else { Some(self.min) }
+    }
+
+    pub fn max(&self) -> Option<u32> {
+        if self.count == 0 { None } else { Some(self.max) }
+    }
+
+    /// The mean round-trip time, rounded down
+    pub fn average(&self) -> Option<u32> {
+        if self.count == 0 { None } else { Some((self.total / self.count as u64) as u32) }
+    }
+
+    /// Clears the measurements, keeping any outstanding request
+    pub fn reset(&mut self) {
+        *self = Self { outstanding: self.outstanding, ..Self::default() };
+    }
+}
+
+fn increment(counter: &mut u32) {
+    *counter = counter.wrapping_add(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Frame, Header};
+
+    #[test]
+    fn record_parse_test() {
+        let mut stats = Stats::new();
+        stats.record_parse(&Frame::parse(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54]));
+        stats.record_parse(&Frame::parse(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x55]));
+        stats.record_parse(&Frame::parse(&[0x00]));
+        stats.record_parse(&Frame::parse(&[0xfc, 0x7a]));
+
+        assert_eq!(Stats {
+            frames_parsed: 1,
+            checksum_failures: 1,
+            resyncs: 1,
+            ..Stats::default()
+        }, stats);
+
+        stats.reset();
+        assert_eq!(Stats::default(), stats);
+    }
+
+    #[test]
+    fn record_scan_test() {
+        let mut buf = [0u8; 11];
+        buf[..4].copy_from_slice(&[0xfc, 0x00, 0xfc, 0x7a]);
+        buf[4..].copy_from_slice(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x55]);
+
+        let mut stats = Stats::new();
+        let (_, result, scan) = Frame::scan_with_stats(&buf, Header::CN105);
+        stats.record_scan(&result, &scan);
+        assert_eq!(Stats { resyncs: 2, checksum_failures: 1, ..Stats::default() }, stats);
+    }
+
+    #[test]
+    fn record_scan_unknown_type_test() {
+        let buf = [0x00, 0xfc, 0x7b, 0x01, 0x30, 0x01, 0x00, 0x53];
+
+        let mut stats = Stats::new();
+        let (consumed, result, scan) = Frame::scan_with_stats(&buf, Header::CN105);
+        stats.record_scan(&result, &scan);
+        assert_eq!(buf.len(), consumed);
+        assert_eq!(Stats { frames_parsed: 1, ..Stats::default() }, stats);
+    }
+
+    #[test]
+    fn link_quality_test() {
+        let mut quality = LinkQuality::new(4, 25, 50);
+
+        for _ in 0..3 { assert_eq!(None, quality.record_frame(true)); }
+        assert_eq!(None, quality.record_frame(true));
+        assert_eq!(LinkHealth::Good, quality.health());
+
+        for _ in 0..3 { assert_eq!(None, quality.record_frame(true)); }
+        assert_eq!(Some(LinkHealth::Marginal), quality.record_frame(false));
+
+        for _ in 0..2 { assert_eq!(None, quality.record_frame(false)); }
+        assert_eq!(None, quality.record_frame(true));
+        assert_eq!(Some(LinkHealth::Bad), quality.record_frame(true));
+
+        for _ in 0..4 { quality.record_frame(true); }
+        assert_eq!(LinkHealth::Good, quality.health());
+    }
+
+    #[test]
+    fn round_trip_times_test() {
+        let mut rtt = RoundTripTimes::new();
+        assert_eq!(None, rtt.average());
+        assert_eq!(None, rtt.record_response(10));
+
+        rtt.record_sent(20);
+        rtt.record_timeout();
+        assert_eq!(None, rtt.record_response(30));
+
+        rtt.record_sent(u32::MAX);
+        assert_eq!(Some(11), rtt.record_response(10));
+        assert_eq!(1, rtt.count());
+
+        rtt.record_sent(40);
+        rtt.reset();
+        assert_eq!(None, rtt.min());
+        assert_eq!(Some(5), rtt.record_response(45));
+    }
+}
@@ -0,0 +1,562 @@
+//! Raw frame and byte tracing
+//!
+//! Fixed-size ring buffers recording recent traffic, so field issues can be
+//! diagnosed after the fact by dumping them to RTT or a debug console.
+//! `FrameTrace` records whole frames sent and received, and `RxHistory`
+//! records every byte received, including junk that never formed a frame.
+//! Any `FrameSink`, a `FrameTrace` included, can be handed copies of frames
+//! as they pass.
+//! `TimingCheck` looks through recorded frames for breaches of the link's
+//! timings, and `Replay` feeds a recorded session back through the code which
+//! produced it.
+//!
+//! ```
+//! use mitsu_ac::trace::{FrameTrace, Direction};
+//!
+//! let mut trace: FrameTrace<8> = FrameTrace::new();
+//! trace.record(Direction::Tx, 100, &[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], true);
+//! trace.record(Direction::Rx, 112, &[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54], true);
+//!
+//! let mut out = String::new();
+//! trace.dump(&mut out).unwrap();
+//! assert_eq!(out.lines().count(), 2);
+//! ```
+
+use core::fmt::{self, Write};
+use core::iter::Peekable;
+
+use crate::link::LinkConfig;
+use crate::protocol::{verify_checksum, DataType};
+
+/// The most bytes recorded for any single frame. Longer frames are truncated,
+/// but their full length is still recorded.
+pub const MAX_TRACED_BYTES: usize = 22;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    Tx,
+    Rx,
+}
+
+/// A single traced frame
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TraceEntry {
+    pub direction: Direction,
+    pub tick: u32,
+    pub checksum_ok: bool,
+    len: usize,
+    bytes: [u8; MAX_TRACED_BYTES],
+}
+
+impl TraceEntry {
+    /// The recorded bytes, which may be shorter than the original frame
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len.min(MAX_TRACED_BYTES)]
+    }
+
+    /// The length of the original frame
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.len > MAX_TRACED_BYTES
+    }
+}
+
+/// Records the last `N` frames. Once full, each new frame overwrites the
+/// oldest.
+#[derive(Debug)]
+pub struct FrameTrace<const N: usize> {
+    entries: [Option<TraceEntry>; N],
+    next: usize,
+}
+
+impl<const N: usize> FrameTrace<N> {
+    pub fn new() -> Self {
+        Self {
+            entries: [None; N],
+            next: 0,
+        }
+    }
+
+    /// Records a frame. `tick` is any monotonic timestamp meaningful to the
+    /// caller.
+    pub fn record(&mut self, direction: Direction, tick: u32, frame: &[u8], checksum_ok: bool) {
+        if N == 0 { return; }
+
+        let mut bytes = [0u8; MAX_TRACED_BYTES];
+        let recorded = frame.len().min(MAX_TRACED_BYTES);
+        bytes[..recorded].copy_from_slice(&frame[..recorded]);
+
+        self.entries[self.next] = Some(TraceEntry {
+            direction,
+            tick,
+            checksum_ok,
+            len: frame.len(),
+            bytes,
+        });
+        self.next = (self.next + 1) % N;
+    }
+
+    /// Iterates over the recorded frames, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &TraceEntry> {
+        let (newest, oldest) = self.entries.split_at(self.next);
+        oldest.iter().chain(newest.iter()).filter_map(Option::as_ref)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+        self.next = 0;
+    }
+
+    /// Writes one line per recorded frame, oldest first:
+    ///
+    /// ```text
+    /// 112 RX ok fc 7a 01 30 01 00 54
+    /// ```
+    pub fn dump<W: Write>(&self, w: &mut W) -> fmt::Result {
+        for entry in self.iter() {
+            let direction = match entry.direction { Direction::Tx => "TX", Direction::Rx => "RX" };
+            let checksum = if entry.checksum_ok { "ok" } else { "BAD" };
+            write!(w, "{} {} {}", entry.tick, direction, checksum)?;
+            for b in entry.bytes() {
+                write!(w, " {:02x}", b)?;
+            }
+            if entry.is_truncated() {
+                write!(w, " ... ({} bytes)", entry.len())?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for FrameTrace<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Somewhere to mirror a copy of every frame sent and received, such as a
+/// log on an SD card or a second UART forwarding to a PC. Code driving a
+/// link takes any `FrameSink`, so what's done with the copies can change
+/// without touching it. Closures taking the same arguments are sinks, as is
+/// `FrameTrace`.
+///
+/// ```
+/// use mitsu_ac::protocol::ParsedFrame;
+/// use mitsu_ac::trace::{Direction, FrameSink, FrameTrace};
+///
+/// fn receive<S: FrameSink>(rx: &[u8], tick: u32, sink: &mut S) {
+///     if let Ok((_, frame)) = ParsedFrame::parse(rx) {
+///         sink.frame(Direction::Rx, tick, frame.raw());
+///         // ...
+///     }
+/// }
+///
+/// let mut trace: FrameTrace<8> = FrameTrace::new();
+/// receive(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54], 10, &mut trace);
+/// assert!(trace.iter().next().unwrap().checksum_ok);
+///
+/// let mut frames = 0;
+/// receive(&[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54], 20, &mut |_, _, _: &[u8]| frames += 1);
+/// assert_eq!(1, frames);
+/// ```
+pub trait FrameSink {
+    /// Takes a copy of a frame sent or received at `tick`
+    fn frame(&mut self, direction: Direction, tick: u32, frame: &[u8]);
+}
+
+impl<const N: usize> FrameSink for FrameTrace<N> {
+    fn frame(&mut self, direction: Direction, tick: u32, frame: &[u8]) {
+        self.record(direction, tick, frame, verify_checksum(frame).is_ok());
+    }
+}
+
+impl<F: FnMut(Direction, u32, &[u8])> FrameSink for F {
+    fn frame(&mut self, direction: Direction, tick: u32, frame: &[u8]) {
+        self(direction, tick, frame)
+    }
+}
+
+/// Records the last `N` bytes received, whether or not they formed a valid
+/// frame. Useful for spotting baud rate or parity misconfiguration, which
+/// tends to produce no frames at all.
+///
+/// ```
+/// use mitsu_ac::trace::RxHistory;
+///
+/// let mut history: RxHistory<4> = RxHistory::new();
+/// history.extend(&[0x01, 0x02, 0x03, 0x04, 0x05]);
+///
+/// let mut out = String::new();
+/// history.dump(&mut out).unwrap();
+/// assert_eq!(out, "02 03 04 05\n");
+/// ```
+#[derive(Debug)]
+pub struct RxHistory<const N: usize> {
+    bytes: [u8; N],
+    next: usize,
+    full: bool,
+}
+
+impl<const N: usize> RxHistory<N> {
+    pub fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            next: 0,
+            full: false,
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        if N == 0 { return; }
+
+        self.bytes[self.next] = byte;
+        self.next = (self.next + 1) % N;
+        if self.next == 0 { self.full = true; }
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.push(*b);
+        }
+    }
+
+    /// Iterates over the recorded bytes, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &u8> {
+        let (newest, oldest) = self.bytes.split_at(self.next);
+        let oldest = if self.full { oldest } else { &oldest[..0] };
+        oldest.iter().chain(newest.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        if self.full { N } else { self.next }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.next = 0;
+        self.full = false;
+    }
+
+    /// Writes the recorded bytes as hex, oldest first, 16 to a line
+    pub fn dump<W: Write>(&self, w: &mut W) -> fmt::Result {
+        for (i, b) in self.iter().enumerate() {
+            if i % 16 != 0 { write!(w, " ")?; }
+            write!(w, "{:02x}", b)?;
+            if i % 16 == 15 || i + 1 == self.len() { writeln!(w)?; }
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for RxHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A breach of a link's timings, found by `TimingCheck`. Ticks are those of
+/// the frames involved.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TimingViolation {
+    /// The response to the request sent at `sent` took `latency` ticks,
+    /// longer than `response_timeout_ms`
+    SlowResponse { sent: u32, latency: u32 },
+    /// The request sent at `sent` got no response before the next was sent
+    /// at `next`
+    NoResponse { sent: u32, next: u32 },
+    /// A frame was sent at `tick`, only `gap` ticks after the one before,
+    /// less than `inter_frame_gap_ms`
+    TooSoon { tick: u32, gap: u32 },
+    /// A frame was sent at `tick`, only `gap` ticks after a `ConnectRequest`,
+    /// less than `connect_delay_ms`
+    TooSoonAfterConnect { tick: u32, gap: u32 },
+}
+
+/// Checks the timing of frames sent and received against a `LinkConfig`,
+/// taking ticks to be milliseconds. Useful on a `FrameTrace` recorded from a
+/// unit which drops requests, to see whether it's being polled too hard.
+///
+/// ```
+/// use mitsu_ac::link;
+/// use mitsu_ac::trace::{Direction, FrameTrace, TimingCheck, TimingViolation};
+///
+/// let mut trace: FrameTrace<8> = FrameTrace::new();
+/// trace.record(Direction::Tx, 0, &[0xfc, 0x42, 0x01, 0x30, 0x10, 0x02], true);
+/// trace.record(Direction::Tx, 400, &[0xfc, 0x42, 0x01, 0x30, 0x10, 0x03], true);
+///
+/// let mut check = TimingCheck::new(link::CN105);
+/// let violations: Vec<_> = trace.iter().flat_map(|entry| check.check(entry)).collect();
+/// assert_eq!(vec![
+///     TimingViolation::NoResponse { sent: 0, next: 400 },
+///     TimingViolation::TooSoon { tick: 400, gap: 400 },
+/// ], violations);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TimingCheck {
+    config: LinkConfig,
+    last_sent: Option<Sent>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+struct Sent {
+    tick: u32,
+    connect: bool,
+    answered: bool,
+}
+
+impl TimingCheck {
+    pub fn new(config: LinkConfig) -> Self {
+        Self { config, last_sent: None }
+    }
+
+    /// Checks the next frame, in the order they were sent and received,
+    /// returning any violations it shows
+    pub fn check(&mut self, entry: &TraceEntry) -> impl Iterator<Item = TimingViolation> {
+        let found = match entry.direction {
+            Direction::Tx => self.check_sent(entry),
+            Direction::Rx => [self.check_received(entry), None],
+        };
+        IntoIterator::into_iter(found).flatten()
+    }
+
+    fn check_sent(&mut self, entry: &TraceEntry) -> [Option<TimingViolation>; 2] {
+        let mut found = [None, None];
+        if let Some(last) = self.last_sent {
+            let gap = entry.tick.wrapping_sub(last.tick);
+            if !last.answered {
+                found[0] = Some(TimingViolation::NoResponse { sent: last.tick, next: entry.tick });
+            }
+            if last.connect && gap < self.config.connect_delay_ms {
+                found[1] = Some(TimingViolation::TooSoonAfterConnect { tick: entry.tick, gap });
+            } else if gap < self.config.inter_frame_gap_ms {
+                found[1] = Some(TimingViolation::TooSoon { tick: entry.tick, gap });
+            }
+        }
+        let connect = entry.bytes().get(1) == Some(&(DataType::ConnectRequest.as_byte()));
+        self.last_sent = Some(Sent { tick: entry.tick, connect, answered: false });
+        found
+    }
+
+    fn check_received(&mut self, entry: &TraceEntry) -> Option<TimingViolation> {
+        let sent = self.last_sent.as_mut().filter(|sent| !sent.answered)?;
+        sent.answered = true;
+        let latency = entry.tick.wrapping_sub(sent.tick);
+        if latency > self.config.response_timeout_ms {
+            Some(TimingViolation::SlowResponse { sent: sent.tick, latency })
+        } else {
+            None
+        }
+    }
+}
+
+/// Where a replayed session went differently from the recording. `index`
+/// counts the recorded frames from the start of the session.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReplayMismatch {
+    /// A frame was sent where the recording has `expected` instead, or
+    /// `None` after the end of the session
+    Unexpected { index: usize, expected: Option<TraceEntry> },
+    /// The recording sent `expected`, but the replay didn't
+    Missing { index: usize, expected: TraceEntry },
+}
+
+/// Feeds a recorded session, such as one from a `FrameTrace`, back through
+/// the code which drives a link, checking it sends the frames it sent at the
+/// time. Frames received are handed out in order with their ticks, and each
+/// frame sent in reply is checked against the next one recorded, so changes
+/// to polling or retry logic can be tested against traffic from a real
+/// unit.
+///
+/// ```
+/// use mitsu_ac::trace::{Direction, FrameTrace, Replay, ReplayMismatch};
+///
+/// let connect = [0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8];
+/// let connected = [0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54];
+/// let info = [0xfc, 0x42, 0x01, 0x30, 0x10, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x7b];
+///
+/// let mut session: FrameTrace<8> = FrameTrace::new();
+/// session.record(Direction::Tx, 0, &connect, true);
+/// session.record(Direction::Rx, 12, &connected, true);
+/// session.record(Direction::Tx, 100, &info, true);
+///
+/// let mut replay = Replay::new(session.iter());
+/// replay.sent(&connect).unwrap();
+/// while let Some(entry) = replay.next_received().unwrap() {
+///     assert_eq!(12, entry.tick);
+///     // Whatever handles a connect response sends a request for the
+///     // settings
+///     replay.sent(&info).unwrap();
+/// }
+/// replay.finish().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replay<'a, I: Iterator<Item = &'a TraceEntry>> {
+    entries: Peekable<I>,
+    index: usize,
+}
+
+impl<'a, I: Iterator<Item = &'a TraceEntry>> Replay<'a, I> {
+    pub fn new<E>(entries: E) -> Self where E: IntoIterator<IntoIter = I> {
+        Self { entries: entries.into_iter().peekable(), index: 0 }
+    }
+
+    /// The next frame received in the session, to feed through. Fails if the
+    /// session sent a frame first which the replay hasn't.
+    pub fn next_received(&mut self) -> Result<Option<&'a TraceEntry>, ReplayMismatch> {
+        match self.entries.peek() {
+            Some(entry) if entry.direction == Direction::Tx => {
+                Err(ReplayMismatch::Missing { index: self.index, expected: **entry })
+            },
+            _ => Ok(self.advance()),
+        }
+    }
+
+    /// Checks a frame sent by the replay against the next one in the
+    /// session. A truncated recording is matched on the bytes recorded and
+    /// its full length.
+    pub fn sent(&mut self, frame: &[u8]) -> Result<(), ReplayMismatch> {
+        match self.entries.peek() {
+            Some(entry) if entry.direction == Direction::Tx
+                && entry.len() == frame.len() && frame.starts_with(entry.bytes()) => {
+                self.advance();
+                Ok(())
+            },
+            expected => Err(ReplayMismatch::Unexpected { index: self.index, expected: expected.map(|entry| **entry) }),
+        }
+    }
+
+    /// Checks the session didn't send anything more which the replay hasn't
+    pub fn finish(mut self) -> Result<(), ReplayMismatch> {
+        while let Some(entry) = self.entries.peek() {
+            if entry.direction == Direction::Tx {
+                return Err(ReplayMismatch::Missing { index: self.index, expected: **entry });
+            }
+            self.advance();
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self) -> Option<&'a TraceEntry> {
+        let entry = self.entries.next()?;
+        self.index += 1;
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_around_test() {
+        let mut trace: FrameTrace<2> = FrameTrace::new();
+        trace.record(Direction::Tx, 1, &[0x01], true);
+        trace.record(Direction::Rx, 2, &[0x02], true);
+        trace.record(Direction::Rx, 3, &[0x03], false);
+
+        let ticks: [u32; 2] = {
+            let mut iter = trace.iter().map(|e| e.tick);
+            [iter.next().unwrap(), iter.next().unwrap()]
+        };
+        assert_eq!([2, 3], ticks);
+        assert_eq!(2, trace.iter().count());
+    }
+
+    #[test]
+    fn truncates_long_frames_test() {
+        let mut trace: FrameTrace<1> = FrameTrace::new();
+        trace.record(Direction::Rx, 0, &[0xaa; 30], false);
+
+        let entry = trace.iter().next().unwrap();
+        assert!(entry.is_truncated());
+        assert_eq!(30, entry.len());
+        assert_eq!(MAX_TRACED_BYTES, entry.bytes().len());
+    }
+
+    #[test]
+    fn rx_history_test() {
+        let mut history: RxHistory<3> = RxHistory::new();
+        assert!(history.is_empty());
+
+        history.extend(&[0x01, 0x02]);
+        assert_eq!(2, history.len());
+        assert!(history.iter().eq([0x01, 0x02].iter()));
+
+        history.extend(&[0x03, 0x04]);
+        assert_eq!(3, history.len());
+        assert!(history.iter().eq([0x02, 0x03, 0x04].iter()));
+    }
+
+    #[test]
+    fn timing_check_test() {
+        let mut trace: FrameTrace<8> = FrameTrace::new();
+        trace.record(Direction::Tx, 0, &[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8], true);
+        trace.record(Direction::Rx, 50, &[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x54], true);
+        trace.record(Direction::Tx, 1500, &[0xfc, 0x42], true);
+        trace.record(Direction::Rx, 2600, &[0xfc, 0x62], true);
+        trace.record(Direction::Rx, 2700, &[0xfc, 0x62], true);
+        trace.record(Direction::Tx, 3000, &[0xfc, 0x42], true);
+
+        let mut check = TimingCheck::new(crate::link::CN105);
+        let mut violations = trace.iter().flat_map(|entry| check.check(entry));
+        assert_eq!(Some(TimingViolation::TooSoonAfterConnect { tick: 1500, gap: 1500 }), violations.next());
+        assert_eq!(Some(TimingViolation::SlowResponse { sent: 1500, latency: 1100 }), violations.next());
+        assert_eq!(None, violations.next());
+    }
+
+    #[test]
+    fn replay_mismatch_test() {
+        let mut session: FrameTrace<4> = FrameTrace::new();
+        session.record(Direction::Tx, 0, &[0x01], true);
+        session.record(Direction::Rx, 1, &[0x02], true);
+        session.record(Direction::Tx, 2, &[0x03], true);
+        let entries: [TraceEntry; 3] = {
+            let mut iter = session.iter().copied();
+            [iter.next().unwrap(), iter.next().unwrap(), iter.next().unwrap()]
+        };
+
+        let mut replay = Replay::new(entries.iter());
+        assert_eq!(Err(ReplayMismatch::Missing { index: 0, expected: entries[0] }), replay.next_received());
+        assert_eq!(Err(ReplayMismatch::Unexpected { index: 0, expected: Some(entries[0]) }), replay.sent(&[0x01, 0x00]));
+        replay.sent(&[0x01]).unwrap();
+        assert_eq!(Some(&entries[1]), replay.next_received().unwrap());
+        assert_eq!(Err(ReplayMismatch::Missing { index: 2, expected: entries[2] }), replay.clone().finish());
+        replay.sent(&[0x03]).unwrap();
+        assert_eq!(Err(ReplayMismatch::Unexpected { index: 3, expected: None }), replay.sent(&[0x03]));
+        assert_eq!(Ok(None), replay.next_received());
+    }
+
+    #[test]
+    fn replay_truncated_test() {
+        let mut session: FrameTrace<1> = FrameTrace::new();
+        session.record(Direction::Tx, 0, &[0xaa; 30], true);
+
+        let mut replay = Replay::new(session.iter());
+        assert!(replay.sent(&[0xaa; 29]).is_err());
+        replay.sent(&[0xaa; 30]).unwrap();
+        replay.finish().unwrap();
+    }
+
+    #[test]
+    fn frame_sink_test() {
+        let mut trace: FrameTrace<2> = FrameTrace::new();
+        trace.frame(Direction::Tx, 0, &[0xfc, 0x5a, 0x01, 0x30, 0x02, 0xca, 0x01, 0xa8]);
+        trace.frame(Direction::Rx, 1, &[0xfc, 0x7a, 0x01, 0x30, 0x01, 0x00, 0x55]);
+
+        let mut iter = trace.iter();
+        assert!(iter.next().unwrap().checksum_ok);
+        let entry = iter.next().unwrap();
+        assert_eq!((Direction::Rx, 1, false), (entry.direction, entry.tick, entry.checksum_ok));
+    }
+}
@@ -0,0 +1,119 @@
+//! Groups of units
+//!
+//! A `Zone` treats several units as one, for whole-house controllers: it
+//! reports on them together and sends one command to all of them.
+//!
+//! ```
+//! # #[cfg(feature = "device")] {
+//! use mitsu_ac::climate::Climate;
+//! use mitsu_ac::protocol::{Device, SetRequest};
+//! use mitsu_ac::protocol::types::{Power, TenthDegreesC};
+//! use mitsu_ac::zone::Zone;
+//!
+//! let mut units = [Device::new(), Device::new()];
+//! units[1].room_temperature = TenthDegreesC(245);
+//!
+//! let mut zone = Zone::new(&mut units);
+//! assert_eq!(Some(TenthDegreesC(245)), zone.max_temperature());
+//!
+//! zone.apply(&SetRequest::power_on()).unwrap();
+//! assert!(zone.units().iter().all(|unit| unit.power() == Some(Power::On)));
+//! # }
+//! ```
+
+use crate::climate::{Action, Climate};
+use crate::protocol::SetRequest;
+use crate::protocol::types::TenthDegreesC;
+
+/// A failure to apply a command to one unit of a `Zone`, at index `unit`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnitError<E> {
+    pub unit: usize,
+    pub error: E,
+}
+
+/// Several units, controlled together
+#[derive(Debug)]
+pub struct Zone<'a, C> {
+    units: &'a mut [C],
+}
+
+impl<'a, C: Climate> Zone<'a, C> {
+    pub fn new(units: &'a mut [C]) -> Self {
+        Self { units }
+    }
+
+    pub fn units(&self) -> &[C] {
+        self.units
+    }
+
+    pub fn units_mut(&mut self) -> &mut [C] {
+        self.units
+    }
+
+    /// The warmest room temperature of any unit, ignoring unknown ones
+    pub fn max_temperature(&self) -> Option<TenthDegreesC> {
+        self.units.iter().filter_map(|unit| unit.current_temperature()).max()
+    }
+
+    /// The coolest room temperature of any unit, ignoring unknown ones
+    pub fn min_temperature(&self) -> Option<TenthDegreesC> {
+        self.units.iter().filter_map(|unit| unit.current_temperature()).min()
+    }
+
+    /// Whether any unit is heating, cooling or drying
+    pub fn any_running(&self) -> bool {
+        self.units.iter().any(|unit| match unit.action() {
+            Action::Heating | Action::Cooling | Action::Drying => true,
+            Action::Off | Action::Idle | Action::Fan => false,
+        })
+    }
+
+    /// Applies `request` to every unit. Units after one which fails are
+    /// still sent it; the first failure is returned.
+    pub fn apply(&mut self, request: &SetRequest) -> Result<(), UnitError<C::Error>> {
+        let mut result = Ok(());
+        for (unit, climate) in self.units.iter_mut().enumerate() {
+            if let Err(error) = climate.apply(request) {
+                if result.is_ok() {
+                    result = Err(UnitError { unit, error });
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::{Fan, Mode, Power, Setpoint};
+
+    /// A unit which refuses every command
+    struct Stuck(Option<TenthDegreesC>);
+
+    impl Climate for Stuck {
+        type Error = ();
+
+        fn current_temperature(&self) -> Option<TenthDegreesC> { self.0 }
+        fn target_temperature(&self) -> Option<Setpoint> { None }
+        fn power(&self) -> Option<Power> { None }
+        fn mode(&self) -> Option<Mode> { None }
+        fn fan(&self) -> Option<Fan> { None }
+        fn action(&self) -> Action { Action::Off }
+        fn apply(&mut self, _request: &SetRequest) -> Result<(), ()> { Err(()) }
+    }
+
+    #[test]
+    fn zone_test() {
+        let mut units = [Stuck(None), Stuck(Some(TenthDegreesC(190))), Stuck(Some(TenthDegreesC(210)))];
+        let mut zone = Zone::new(&mut units);
+
+        assert_eq!(Some(TenthDegreesC(190)), zone.min_temperature());
+        assert_eq!(Some(TenthDegreesC(210)), zone.max_temperature());
+        assert!(!zone.any_running());
+        assert_eq!(Err(UnitError { unit: 0, error: () }), zone.apply(&SetRequest::power_off()));
+
+        assert_eq!(None, Zone::<Stuck>::new(&mut []).max_temperature());
+    }
+}